@@ -1,5 +1,6 @@
-use microQRs::img::{Canvas, BLACK, RED, RGB, WHITE};
-use microQRs::Version;
+use microQRs::error_cc::ErrorLevel;
+use microQRs::img::{Canvas, BLACK, GREEN, GREY, ORANGE, RED, RGB, WHITE};
+use microQRs::{ModuleType, Version};
 use std::env;
 use std::io::stdout;
 use std::process::exit;
@@ -12,39 +13,61 @@ fn main() {
         eprintln!("<version> required, specify version number; ");
         exit(1);
     }
-    let version = match arg.unwrap().as_str() {
-        "1" => Version(1),
-        "2" => Version(2),
-        "3" => Version(3),
-        "4" => Version(4),
-        "5" => Version(5),
-        x => {
-            eprintln!("invalid/unsupported version {}", x);
+    let version = match arg.unwrap().parse::<u8>() {
+        Ok(v @ 1..=40) => Version(v),
+        _ => {
+            eprintln!("invalid/unsupported version, expected 1-40");
+            exit(1);
+        }
+    };
+    //codeword boundaries and ecc words depend on the error level
+    let err_level = match args.next().as_deref() {
+        None | Some("L") => ErrorLevel::L,
+        Some("M") => ErrorLevel::M,
+        Some("Q") => ErrorLevel::Q,
+        Some("H") => ErrorLevel::H,
+        Some(x) => {
+            eprintln!("invalid error level {}, expected one of L, M, Q, H", x);
             exit(1);
         }
     };
 
     let mut ppm_img = Canvas::for_version(version);
-    let res = version.reserved_iter().fold(&mut ppm_img, |acc, module| {
-        const MOD_COLOR: [RGB; 2] = [WHITE, BLACK];
+    const MOD_COLOR: [RGB; 2] = [WHITE, BLACK];
+    for module in version.reserved_iter() {
+        let (x, y) = module.position();
+        ppm_img.set_pixel(
+            x as u32,
+            y as u32,
+            &MOD_COLOR[usize::from(module.is_dark())],
+        );
+    }
+    for module in version
+        .format_modules(err_level, 0)
+        .expect("mask 0 is valid")
+    {
         let (x, y) = module.position();
-        let mod_color = usize::from(module.is_dark());
-        acc.set_pixel(x as u32, y as u32, &MOD_COLOR[mod_color % 2]);
-        acc
-    });
+        ppm_img.set_pixel(x as u32, y as u32, &GREEN);
+    }
 
-    let mut color_iter = rgb_iter()
-        .map(|c| std::iter::repeat(c).take(8))
-        .flat_map(|f| f);
-    version
-        .data_region_iter()
-        .zip(color_iter)
-        .fold(&mut ppm_img, |acc, ((x, y), c)| {
-            acc.set_pixel(x as u32, y as u32, &c);
-            acc
-        });
-    ppm_img.write_header(&mut stdout());
-    ppm_img.write(&mut stdout());
+    //data codewords cycle through the palette, ecc codewords alternate grey and orange
+    let palette: Vec<RGB> = rgb_iter().take(13).collect();
+    for ((x, y), module_type) in version.data_module_types(err_level) {
+        let color = match module_type {
+            ModuleType::DataCodeword { codeword, .. } => palette[codeword as usize % palette.len()],
+            ModuleType::EccCodeword { codeword, .. } if codeword % 2 == 0 => GREY,
+            ModuleType::EccCodeword { .. } => ORANGE,
+            _ => RED,
+        };
+        ppm_img.set_pixel(x as u32, y as u32, &color);
+    }
+    let result = ppm_img
+        .write_header(&mut stdout())
+        .and_then(|_| ppm_img.write(&mut stdout()));
+    if let Err(err) = result {
+        eprintln!("error writing image {}", err);
+        exit(1);
+    }
 }
 
 fn rgb_iter() -> impl Iterator<Item = RGB> {
@@ -1,3 +1,4 @@
+use microQRs::error_cc::ErrorLevel;
 use microQRs::img::{BLACK, WHITE};
 use std::env;
 use std::io::stdout;
@@ -5,16 +6,26 @@ use std::io::stdout;
 ///
 /// simple usage for qr library
 /// Reads text from stdin and output a QR code in PPM format https://en.wikipedia.org/wiki/Netpbm
+/// optional second arg selects error correction level L, M, Q or H (default L)
 fn main() {
     let mut args = env::args();
     if let Some(data) = args.nth(1) {
-        let result = microQRs::encode::<144>(&data);
-        if let Ok(code) = result {
-            microQRs::img::ppm::to_img(&code, [WHITE, BLACK], &mut stdout());
-        } else {
-            eprintln!("encode err ");
+        let err_level = match args.next().as_deref() {
+            None | Some("L") => ErrorLevel::L,
+            Some("M") => ErrorLevel::M,
+            Some("Q") => ErrorLevel::Q,
+            Some("H") => ErrorLevel::H,
+            Some(x) => {
+                eprintln!("invalid error level {}, expected one of L, M, Q, H", x);
+                return;
+            }
+        };
+        let result = microQRs::encode(&data, err_level)
+            .and_then(|code| microQRs::img::ppm::to_img(&code, [WHITE, BLACK], &mut stdout()));
+        if let Err(err) = result {
+            eprintln!("encode err {}", err);
         }
     } else {
-        println!("usage simple <text-to-encode> ");
+        println!("usage simple <text-to-encode> [L|M|Q|H]");
     }
 }
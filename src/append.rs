@@ -0,0 +1,206 @@
+use crate::bits::BigEndianBitWriter;
+use crate::error_cc::ErrorLevel;
+use crate::EncodingErr::{InvalidStructuredAppend, ParityMismatch};
+use crate::{
+    fit_segments, write_segments, Code, EncodingErr, FittedSegments, SegmentHeaders, Version,
+};
+
+pub(crate) const SEG_MODE_STRUCTURED_APPEND: u8 = 0b0011;
+
+//header of one symbol in a sequence of up to 16 that together hold a single message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StructuredAppend {
+    pub index: u8,
+    pub total: u8,
+    pub parity: u8, //xor of every byte of the whole message
+}
+
+impl StructuredAppend {
+    pub const MAX_SYMBOLS: usize = 16;
+    //mode indicator, 4 bit index, 4 bit total - 1 and parity byte
+    pub const SEGMENT_BITS: usize = 20;
+
+    pub(crate) fn write(&self, w: &mut BigEndianBitWriter) -> Result<(), EncodingErr> {
+        w.append_bits(SEG_MODE_STRUCTURED_APPEND as u32, 4)?;
+        w.append_bits(self.index as u32, 4)?;
+        w.append_bits(self.total as u32 - 1, 4)?;
+        w.append_bits(self.parity as u32, 8)?;
+        Ok(())
+    }
+}
+
+pub fn parity(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, b| acc ^ b)
+}
+
+// split data across the fewest symbols, no larger than max_version, that hold it
+pub fn encode_structured_append(
+    data: &str,
+    err_level: ErrorLevel,
+    max_version: Version,
+) -> Result<Vec<Code>, EncodingErr> {
+    let mut codes = Vec::new();
+    for (version, headers, segments) in split(data, err_level, max_version)? {
+        let mut encoded = vec![0; version.code_words()];
+        let size = write_segments(headers, &segments, version, &mut encoded)?;
+        codes.push(Code::from_data_words(
+            encoded, size, version, err_level, None,
+        )?);
+    }
+    Ok(codes)
+}
+
+//chunks of near equal byte length, split on char boundaries
+fn split(
+    data: &str,
+    err_level: ErrorLevel,
+    max_version: Version,
+) -> Result<Vec<FittedSegments<'_>>, EncodingErr> {
+    let parity = parity(data.as_bytes());
+    let max_symbols = StructuredAppend::MAX_SYMBOLS.min(data.len().max(1));
+    for total in 1..=max_symbols {
+        let boundary = |i: usize| {
+            let mut b = i * data.len() / total;
+            while !data.is_char_boundary(b) {
+                b += 1;
+            }
+            b
+        };
+        let symbols: Result<Vec<_>, _> = (0..total)
+            .map(|i| {
+                let chunk = &data[boundary(i)..boundary(i + 1)];
+                let headers = SegmentHeaders {
+                    append: Some(StructuredAppend {
+                        index: i as u8,
+                        total: total as u8,
+                        parity,
+                    }),
+                    ..Default::default()
+                };
+                fit_segments(chunk, headers, max_version, err_level)
+            })
+            .collect();
+        match symbols {
+            Ok(symbols) => return Ok(symbols),
+            Err(err) if total == max_symbols => return Err(err),
+            Err(_) => {}
+        }
+    }
+    Err(InvalidStructuredAppend)
+}
+
+// join the data of decoded symbols, given in any order, back into the original message
+pub fn reassemble(parts: &[(StructuredAppend, &[u8])]) -> Result<Vec<u8>, EncodingErr> {
+    let Some((first, _)) = parts.first() else {
+        return Err(InvalidStructuredAppend);
+    };
+    let total = first.total as usize;
+    if total != parts.len() || total > StructuredAppend::MAX_SYMBOLS {
+        return Err(InvalidStructuredAppend);
+    }
+    let mut ordered: [Option<&[u8]>; StructuredAppend::MAX_SYMBOLS] = Default::default();
+    for (header, data) in parts {
+        if header.total != first.total || header.parity != first.parity {
+            return Err(InvalidStructuredAppend);
+        }
+        let slot = ordered
+            .get_mut(header.index as usize)
+            .filter(|_| (header.index as usize) < total)
+            .ok_or(InvalidStructuredAppend)?;
+        if slot.replace(data).is_some() {
+            return Err(InvalidStructuredAppend);
+        }
+    }
+    let message: Vec<u8> = ordered[0..total]
+        .iter()
+        .flatten()
+        .flat_map(|d| d.iter().copied())
+        .collect();
+    if parity(&message) != first.parity {
+        return Err(ParityMismatch);
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod append_tests {
+    use crate::append::{encode_structured_append, parity, reassemble, split, StructuredAppend};
+    use crate::error_cc::ErrorLevel;
+    use crate::{EncodingErr, Version};
+
+    const CONFIG: &str = "wifi:ssid=warehouse-7;psk=correct horse battery staple;\
+        mqtt=broker.example.com:8883;topic=sensors/line-4/temperature;interval=30";
+
+    #[test]
+    fn test_split_across_symbols() {
+        let symbols = split(CONFIG, ErrorLevel::M, Version(2)).unwrap();
+        assert!(symbols.len() > 1);
+        let total = symbols.len() as u8;
+        let mut message = String::new();
+        for (i, (version, headers, segments)) in symbols.iter().enumerate() {
+            let header = headers.append.unwrap();
+            assert_eq!(header.index, i as u8);
+            assert_eq!(header.total, total);
+            assert_eq!(header.parity, parity(CONFIG.as_bytes()));
+            assert!(version.0 <= 2);
+            segments.iter().for_each(|(_, s)| message.push_str(s));
+        }
+        assert_eq!(message, CONFIG);
+        //fits in one symbol when larger versions are allowed
+        assert_eq!(split(CONFIG, ErrorLevel::M, Version::MAX).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_encode_structured_append() {
+        let codes = encode_structured_append(CONFIG, ErrorLevel::M, Version(2)).unwrap();
+        let total = codes.len() as u8;
+        let parity = parity(CONFIG.as_bytes());
+        for (i, code) in codes.iter().enumerate() {
+            //0011, index, total - 1, parity
+            let words = code.code_words();
+            assert_eq!(words[0], (0b0011 << 4) | i as u8);
+            assert_eq!(words[1], ((total - 1) << 4) | (parity >> 4));
+            assert_eq!(words[2] >> 4, parity & 0xF);
+        }
+        assert!(matches!(
+            encode_structured_append(&CONFIG.repeat(4), ErrorLevel::H, Version(1)),
+            Err(EncodingErr::DataTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reassemble() {
+        let parts = ["config ", "split in ", "three"];
+        let parity = parity(parts.concat().as_bytes());
+        let header = |index| StructuredAppend {
+            index,
+            total: 3,
+            parity,
+        };
+        let shuffled = [
+            (header(2), parts[2].as_bytes()),
+            (header(0), parts[0].as_bytes()),
+            (header(1), parts[1].as_bytes()),
+        ];
+        assert_eq!(reassemble(&shuffled).unwrap(), b"config split in three");
+
+        assert!(matches!(
+            reassemble(&shuffled[0..2]),
+            Err(EncodingErr::InvalidStructuredAppend)
+        ));
+        let duplicate = [shuffled[0], shuffled[1], shuffled[1]];
+        assert!(matches!(
+            reassemble(&duplicate),
+            Err(EncodingErr::InvalidStructuredAppend)
+        ));
+        let corrupt = [
+            shuffled[0],
+            shuffled[1],
+            (header(1), b"split on ".as_slice()),
+        ];
+        assert!(matches!(
+            reassemble(&corrupt),
+            Err(EncodingErr::ParityMismatch)
+        ));
+    }
+}
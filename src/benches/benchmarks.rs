@@ -33,7 +33,7 @@ mod bench {
     fn bench_code_bytes_to_qrcode_v5(b: &mut Bencher) {
         let data_str =  "Unless the Lord builds the house,the builders labor in vain. Psalm-127 www.biblegateway.com/passage";
         b.iter(|| {
-            let result = test::black_box(microQRs::encode::<144>(data_str));
+            let result = test::black_box(microQRs::encode(data_str, ErrorLevel::L));
             if let Ok(code) = result {
                 test::black_box(&code);
             } else {
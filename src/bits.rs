@@ -1,3 +1,6 @@
+use crate::EncodingErr;
+use crate::EncodingErr::{BufferTooSmall, InvalidBitCount};
+
 //iterate over bits in byte, starting with msb bit
 pub struct MsbBitIter<'a> {
     bytes: &'a [u8],
@@ -6,12 +9,12 @@ pub struct MsbBitIter<'a> {
 }
 
 impl<'a> MsbBitIter<'a> {
-    pub fn new(bytes: &[u8]) -> MsbBitIter {
-        return MsbBitIter {
+    pub fn new(bytes: &[u8]) -> MsbBitIter<'_> {
+        MsbBitIter {
             bytes,
             index: 0,
             bit_index: 8, //start at most significant bit first
-        };
+        }
     }
 }
 
@@ -43,18 +46,27 @@ pub struct BigEndianBitWriter<'a> {
 }
 
 impl<'a> BigEndianBitWriter<'a> {
-    pub fn new(buffer: &mut [u8]) -> BigEndianBitWriter {
+    pub fn new(buffer: &mut [u8]) -> BigEndianBitWriter<'_> {
         BigEndianBitWriter {
             bytes: buffer,
             current_bit: 0,
         }
     }
 
-    pub fn append_bits(&mut self, data: u8, num_of_bits: u8) {
+    //append lowest num_of_bits of data, msb first, bits above 32 are zero
+    //nothing is written when the bits do not fit in the buffer
+    pub fn append_bits(&mut self, data: u32, num_of_bits: u8) -> Result<(), EncodingErr> {
+        let end_bit = self.current_bit as usize + num_of_bits as usize;
+        if end_bit > self.bytes.len() * 8 {
+            return Err(BufferTooSmall {
+                needed_bytes: end_bit.div_ceil(8),
+                available_bytes: self.bytes.len(),
+            });
+        }
         let mut slot = (self.current_bit / 8) as usize;
         let mut bit_index = self.current_bit % 8;
         for i in (0..num_of_bits).rev() {
-            let is_bit_set = (data & (1 << i)) != 0;
+            let is_bit_set = i < 32 && (data & (1 << i)) != 0;
             let mask = 1 << (7 - bit_index);
             if is_bit_set {
                 self.bytes[slot] |= mask;
@@ -70,6 +82,7 @@ impl<'a> BigEndianBitWriter<'a> {
             }
         }
         self.current_bit = ((slot * 8) + bit_index as usize) as u32;
+        Ok(())
     }
 
     pub fn bits_written(&self) -> usize {
@@ -77,9 +90,56 @@ impl<'a> BigEndianBitWriter<'a> {
     }
 }
 
+//reads bits from slice in big endian bit order, the inverse of BigEndianBitWriter
+//eg read_bits(4) on bytes[0] = 0b00100000 (32) -> 0b0010
+pub struct BigEndianBitReader<'a> {
+    bytes: &'a [u8],
+    current_bit: usize,
+}
+
+impl<'a> BigEndianBitReader<'a> {
+    pub fn new(bytes: &[u8]) -> BigEndianBitReader<'_> {
+        BigEndianBitReader {
+            bytes,
+            current_bit: 0,
+        }
+    }
+
+    //next num_of_bits as the lowest bits of the result, msb first, at most 32
+    //nothing is read when fewer bits remain or more than 32 are asked for
+    pub fn read_bits(&mut self, num_of_bits: u8) -> Result<u32, EncodingErr> {
+        if num_of_bits > 32 {
+            return Err(InvalidBitCount(num_of_bits));
+        }
+        let end_bit = self.current_bit + num_of_bits as usize;
+        if end_bit > self.bytes.len() * 8 {
+            return Err(BufferTooSmall {
+                needed_bytes: end_bit.div_ceil(8),
+                available_bytes: self.bytes.len(),
+            });
+        }
+        let mut value = 0u64;
+        for i in self.current_bit..end_bit {
+            let bit = (self.bytes[i / 8] >> (7 - i % 8)) & 1;
+            value = (value << 1) | bit as u64;
+        }
+        self.current_bit = end_bit;
+        Ok(value as u32)
+    }
+
+    pub fn bits_read(&self) -> usize {
+        self.current_bit
+    }
+
+    pub fn bits_remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.current_bit
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::bits::{BigEndianBitWriter, MsbBitIter};
+    use crate::bits::{BigEndianBitReader, BigEndianBitWriter, MsbBitIter};
+    use crate::EncodingErr;
 
     #[test]
     fn test_bit_msb_iter() {
@@ -100,15 +160,14 @@ mod tests {
     fn test_bit_writer() {
         let mut bit_buff = [0; 8];
         let mut bit_writer = BigEndianBitWriter::new(&mut bit_buff);
-        bit_writer.append_bits(0b0100, 4);
+        bit_writer.append_bits(0b0100, 4).unwrap();
 
         assert_eq!(bit_writer.bits_written(), 4);
-        bit_writer.append_bits(0b00000110, 8);
-        bit_writer.append_bits(0b0110, 4);
+        bit_writer.append_bits(0b00000110, 8).unwrap();
+        bit_writer.append_bits(0b0110, 4).unwrap();
         assert_eq!(bit_writer.bits_written(), 16);
         let mut actual = Vec::new();
-        for i in 0..2 {
-            let byte = bit_buff[i];
+        for byte in &bit_buff[0..2] {
             for j in (0..8).rev() {
                 let val = if (byte & (1 << j)) == 0 { '0' } else { '1' };
                 actual.push(val);
@@ -117,4 +176,83 @@ mod tests {
         let expected: Vec<char> = "0100000001100110".chars().collect();
         assert_eq!(&expected, &actual)
     }
+
+    #[test]
+    fn test_bit_writer_wide_values() {
+        let mut bit_buff = [0; 4];
+        let mut bit_writer = BigEndianBitWriter::new(&mut bit_buff);
+        bit_writer.append_bits(0b0001, 4).unwrap();
+        bit_writer.append_bits(0b1100001100, 10).unwrap();
+        bit_writer.append_bits(0x3FFF, 14).unwrap();
+        assert_eq!(bit_writer.bits_written(), 28);
+        assert_eq!(&bit_buff, &[0b00011100, 0b00110011, 0b11111111, 0b11110000]);
+    }
+
+    #[test]
+    fn test_bit_writer_buffer_too_small() {
+        let mut bit_buff = [0; 2];
+        let mut bit_writer = BigEndianBitWriter::new(&mut bit_buff);
+        bit_writer.append_bits(0xABC, 12).unwrap();
+        assert!(matches!(
+            bit_writer.append_bits(0x1F, 5),
+            Err(EncodingErr::BufferTooSmall {
+                needed_bytes: 3,
+                available_bytes: 2
+            })
+        ));
+        assert_eq!(bit_writer.bits_written(), 12);
+        bit_writer.append_bits(0xF, 4).unwrap();
+        assert_eq!(&bit_buff, &[0xAB, 0xCF]);
+    }
+
+    #[test]
+    fn test_bit_reader() {
+        let bytes = [0b00011100, 0b00110011, 0b11111111, 0b11110000];
+        let mut reader = BigEndianBitReader::new(&bytes);
+        assert_eq!(reader.read_bits(4).unwrap(), 0b0001);
+        assert_eq!(reader.read_bits(10).unwrap(), 0b1100001100);
+        assert_eq!(reader.read_bits(0).unwrap(), 0);
+        assert_eq!(reader.read_bits(14).unwrap(), 0x3FFF);
+        assert_eq!(reader.bits_read(), 28);
+        assert_eq!(reader.bits_remaining(), 4);
+        assert!(matches!(
+            reader.read_bits(5),
+            Err(EncodingErr::BufferTooSmall {
+                needed_bytes: 5,
+                available_bytes: 4
+            })
+        ));
+        assert_eq!(reader.bits_read(), 28);
+        assert_eq!(reader.read_bits(4).unwrap(), 0);
+        assert_eq!(reader.bits_remaining(), 0);
+
+        let mut reader = BigEndianBitReader::new(&[0xDE, 0xAD, 0xBE, 0xEF, 0x80]);
+        assert_eq!(reader.read_bits(32).unwrap(), 0xDEADBEEF);
+        assert_eq!(reader.read_bits(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_bit_reader_over_32_bits() {
+        let mut reader = BigEndianBitReader::new(&[0xFF; 8]);
+        assert!(matches!(
+            reader.read_bits(33),
+            Err(EncodingErr::InvalidBitCount(33))
+        ));
+        assert_eq!(reader.bits_read(), 0);
+        assert_eq!(reader.read_bits(32).unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn test_bit_reader_reads_writer_output() {
+        let mut bit_buff = [0; 6];
+        let fields = [(0b0100, 4), (0x2A, 8), (0x1FFF, 13), (0, 3), (0x5412, 15)];
+        let mut bit_writer = BigEndianBitWriter::new(&mut bit_buff);
+        for (value, bits) in fields {
+            bit_writer.append_bits(value, bits).unwrap();
+        }
+        let mut reader = BigEndianBitReader::new(&bit_buff);
+        for (value, bits) in fields {
+            assert_eq!(reader.read_bits(bits).unwrap(), value);
+        }
+    }
 }
@@ -0,0 +1,217 @@
+use crate::error_cc::ErrorLevel;
+use crate::EncodingErr::{DataTooLong, InvalidMask, UnsupportedVersion};
+use crate::{
+    has_fnc1, segment_list_bits, write_segment_list, Code, EncodingErr, Segment, Version, MASK_FN,
+};
+
+//symbol assembled from segments written exactly in the order pushed
+pub struct QrBuilder<'a> {
+    segments: Vec<Segment<'a>>,
+    err_level: ErrorLevel,
+    min_version: Version,
+    max_version: Version,
+    mask: Option<u8>,
+    boost_err_level: bool,
+}
+
+impl Default for QrBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> QrBuilder<'a> {
+    pub fn new() -> QrBuilder<'a> {
+        QrBuilder {
+            segments: Vec::new(),
+            err_level: ErrorLevel::L,
+            min_version: Version(1),
+            max_version: Version::MAX,
+            mask: None,
+            boost_err_level: false,
+        }
+    }
+
+    pub fn push(&mut self, segment: Segment<'a>) -> &mut Self {
+        self.segments.push(segment);
+        self
+    }
+
+    pub fn error_level(&mut self, err_level: ErrorLevel) -> &mut Self {
+        self.err_level = err_level;
+        self
+    }
+
+    pub fn min_version(&mut self, version: Version) -> &mut Self {
+        self.min_version = version;
+        self
+    }
+
+    pub fn max_version(&mut self, version: Version) -> &mut Self {
+        self.max_version = version;
+        self
+    }
+
+    //raise error level when the chosen version has room for a higher one
+    pub fn boost_error_level(&mut self, boost: bool) -> &mut Self {
+        self.boost_err_level = boost;
+        self
+    }
+
+    //use mask pattern 0-7 instead of the one with lowest penalty
+    pub fn mask(&mut self, mask: u8) -> &mut Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    //smallest version in range that holds the segments
+    pub fn build(&self) -> Result<Code, EncodingErr> {
+        let (min, max) = (self.min_version.0, self.max_version.0);
+        if !self.min_version.is_valid() || min > max {
+            return Err(UnsupportedVersion(min));
+        }
+        if !self.max_version.is_valid() {
+            return Err(UnsupportedVersion(max));
+        }
+        if self.mask.is_some_and(|m| m as usize >= MASK_FN.len()) {
+            return Err(InvalidMask);
+        }
+        let fnc1 = has_fnc1(&self.segments);
+        //errors other than length do not depend on version
+        for segment in &self.segments {
+            segment.validate(self.max_version, fnc1)?;
+        }
+        let version = (min..=max)
+            .map(Version)
+            .find(|v| {
+                self.segments.iter().all(|s| s.validate(*v, fnc1).is_ok())
+                    && self.err_level.data_bits(v.0) >= segment_list_bits(&self.segments, *v)
+            })
+            .ok_or(DataTooLong {
+                needed_bits: segment_list_bits(&self.segments, self.max_version),
+                capacity_bits: self.err_level.data_bits(max),
+            })?;
+        let mut encoded = vec![0; version.code_words()];
+        let size = write_segment_list(&self.segments, version, &mut encoded)?;
+        let err_level = if self.boost_err_level {
+            self.err_level
+                .boosted(version.0, segment_list_bits(&self.segments, version))
+        } else {
+            self.err_level
+        };
+        Code::from_data_words(encoded, size, version, err_level, self.mask)
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use crate::append::StructuredAppend;
+    use crate::builder::QrBuilder;
+    use crate::error_cc::ErrorLevel;
+    use crate::{encode, Eci, EncodingErr, Fnc1, Segment, Version};
+
+    #[test]
+    fn test_build_segments_in_order() {
+        let mut builder = QrBuilder::new();
+        builder
+            .push(Segment::Alphanumeric("INV-"))
+            .push(Segment::Numeric("2024"))
+            .error_level(ErrorLevel::M);
+        let code = builder.build().unwrap();
+        assert_eq!(code.version.0, 1);
+        assert_eq!(code.err_level, ErrorLevel::M);
+        //0010 count 4, INV- then 0001 count 4, 2024
+        let expected_bytes = [0x20, 0x23, 0x41, 0xB3, 0x82, 0x02, 0x19, 0x48, 0x00];
+        assert_eq!(&expected_bytes, &code.code_words()[0..expected_bytes.len()]);
+
+        //same data as one alphanumeric segment
+        let code = QrBuilder::new()
+            .push(Segment::Alphanumeric("INV-2024"))
+            .build()
+            .unwrap();
+        //0010 count 8
+        assert_eq!(code.code_words()[0], 0x20);
+        assert_eq!(code.code_words()[1] >> 3, 0b01000);
+    }
+
+    #[test]
+    fn test_build_headers() {
+        let append = StructuredAppend {
+            index: 1,
+            total: 2,
+            parity: 0xA5,
+        };
+        let code = QrBuilder::new()
+            .push(Segment::StructuredAppend(append))
+            .push(Segment::Eci(Eci(3)))
+            .push(Segment::Fnc1(Fnc1::First))
+            .push(Segment::Bytes(b"A"))
+            .build()
+            .unwrap();
+        //0011 0001 0001 10100101, 0111 00000011, 0101, 0100 00000001 01000001, 0000
+        let expected_bytes = [0x31, 0x1A, 0x57, 0x03, 0x54, 0x01, 0x41, 0x00];
+        assert_eq!(&expected_bytes, &code.code_words()[0..expected_bytes.len()]);
+    }
+
+    #[test]
+    fn test_build_version_range_and_mask() {
+        let mut builder = QrBuilder::new();
+        builder.push(Segment::Bytes(b"hello world"));
+        assert_eq!(builder.build().unwrap().version.0, 1);
+        let code = builder.min_version(Version(3)).mask(5).build().unwrap();
+        assert_eq!(code.version.0, 3);
+        assert_eq!(code.mask, 5);
+
+        let long = "a".repeat(40);
+        let mut builder = QrBuilder::new();
+        builder
+            .push(Segment::Bytes(long.as_bytes()))
+            .max_version(Version(2));
+        assert!(matches!(
+            builder.build(),
+            Err(EncodingErr::DataTooLong {
+                needed_bits: 332,
+                capacity_bits: 272
+            })
+        ));
+        assert_eq!(
+            builder.max_version(Version(3)).build().unwrap().version.0,
+            3
+        );
+
+        let code = QrBuilder::new()
+            .push(Segment::Bytes(b"hello"))
+            .boost_error_level(true)
+            .build()
+            .unwrap();
+        assert_eq!((code.version.0, code.err_level), (1, ErrorLevel::H));
+
+        assert!(matches!(
+            QrBuilder::new().mask(8).build(),
+            Err(EncodingErr::InvalidMask)
+        ));
+        assert!(matches!(
+            QrBuilder::new()
+                .min_version(Version(5))
+                .max_version(Version(4))
+                .build(),
+            Err(EncodingErr::UnsupportedVersion(5))
+        ));
+        assert!(matches!(
+            QrBuilder::new().push(Segment::Numeric("12a")).build(),
+            Err(EncodingErr::NotNumeric)
+        ));
+    }
+
+    #[test]
+    fn test_build_matches_encode() {
+        let data = "isaiah-perumalla";
+        let code = QrBuilder::new()
+            .push(Segment::Bytes(data.as_bytes()))
+            .build()
+            .unwrap();
+        let encoded = encode(data, ErrorLevel::L).unwrap();
+        assert_eq!(code.code_words(), encoded.code_words());
+        assert_eq!(code.mask, encoded.mask);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::error_cc::ErrorLevel;
+use crate::EncodingErr::UnsupportedVersion;
+use crate::{header_segments_bits, optimise, Eci, EncodingErr, Mode, SegmentHeaders, Version};
+
+//a version and error level that holds a payload
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fit {
+    pub version: Version,
+    pub err_level: ErrorLevel,
+    pub spare_bits: usize, //data bits left after the segments
+}
+
+// characters of mode that fit in a single segment of version at err_level
+//bytes mode counts bytes
+pub fn char_capacity(
+    mode: Mode,
+    version: Version,
+    err_level: ErrorLevel,
+) -> Result<usize, EncodingErr> {
+    if !version.is_valid() {
+        return Err(UnsupportedVersion(version.0));
+    }
+    let data_bits = err_level.data_bits(version.0);
+    let max_count = (1usize << mode.char_count_bits(version)) - 1;
+    //segment bits grow with count, binary search for the largest that fits
+    let (mut lo, mut hi) = (0, max_count.min(data_bits));
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if mode.segment_bits(mid, version) <= data_bits {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    Ok(lo)
+}
+
+// every version and error level that holds data as encode would write it, smallest version first
+pub fn fits(data: &str) -> Vec<Fit> {
+    let mut fits = Vec::new();
+    //segments are optimised for the count field widths of each version range
+    for (first, last) in [(1, 9), (10, 26), (27, 40)] {
+        let segments =
+            optimise::optimal_segments(data, Version(last), true, false, Eci::UTF8.segment_bits());
+        let headers = SegmentHeaders {
+            eci: Eci::for_segments(&segments),
+            ..Default::default()
+        };
+        for version in (first..=last).map(Version) {
+            let bits = header_segments_bits(headers, &segments, version);
+            for err_level in ErrorLevel::ALL {
+                let data_bits = err_level.data_bits(version.0);
+                if data_bits >= bits {
+                    fits.push(Fit {
+                        version,
+                        err_level,
+                        spare_bits: data_bits - bits,
+                    });
+                }
+            }
+        }
+    }
+    fits
+}
+
+#[cfg(test)]
+mod capacity_tests {
+    use crate::capacity::{char_capacity, fits, Fit};
+    use crate::error_cc::ErrorLevel;
+    use crate::{EncodingErr, Mode, Version};
+
+    const MODES: [Mode; 4] = [Mode::Numeric, Mode::Alphanumeric, Mode::Bytes, Mode::Kanji];
+
+    #[test]
+    fn test_char_capacity() {
+        //ISO/IEC 18004 table 7
+        let expected = [
+            (1, ErrorLevel::L, [41, 25, 17, 10]),
+            (1, ErrorLevel::H, [17, 10, 7, 4]),
+            (10, ErrorLevel::M, [513, 311, 213, 131]),
+            (40, ErrorLevel::L, [7089, 4296, 2953, 1817]),
+            (40, ErrorLevel::H, [3057, 1852, 1273, 784]),
+        ];
+        for (version, level, chars) in expected {
+            let capacity: Vec<usize> = MODES
+                .iter()
+                .map(|m| char_capacity(*m, Version(version), level).unwrap())
+                .collect();
+            assert_eq!(capacity, chars, "version {version} {level:?}");
+        }
+        assert!(matches!(
+            char_capacity(Mode::Bytes, Version(41), ErrorLevel::L),
+            Err(EncodingErr::UnsupportedVersion(41))
+        ));
+        assert!(matches!(
+            char_capacity(Mode::Bytes, Version(0), ErrorLevel::L),
+            Err(EncodingErr::UnsupportedVersion(0))
+        ));
+    }
+
+    #[test]
+    fn test_fits() {
+        //74 bits alphanumeric
+        let fits = fits("HELLO WORLD");
+        assert_eq!(
+            fits[0..4],
+            [
+                Fit {
+                    version: Version(1),
+                    err_level: ErrorLevel::L,
+                    spare_bits: 78
+                },
+                Fit {
+                    version: Version(1),
+                    err_level: ErrorLevel::M,
+                    spare_bits: 54
+                },
+                Fit {
+                    version: Version(1),
+                    err_level: ErrorLevel::Q,
+                    spare_bits: 30
+                },
+                Fit {
+                    version: Version(2),
+                    err_level: ErrorLevel::L,
+                    spare_bits: 198
+                },
+            ]
+        );
+        assert_eq!(fits.len(), 4 * 40 - 1);
+        assert!(crate::capacity::fits(&"a".repeat(3000)).is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+use crate::append::{StructuredAppend, SEG_MODE_STRUCTURED_APPEND};
+use crate::bits::BigEndianBitReader;
+use crate::error_cc::ErrorLevel;
+use crate::matrix::QrMatrix;
+use crate::EncodingErr::{InvalidData, InvalidFormat, InvalidMatrix, UnsupportedVersion};
+use crate::{
+    sjis, Eci, EncodingErr, Fnc1, Mode, Segment, Version, ALPHANUMERIC_CHARS, MASK_FN,
+    SEG_MODE_ECI, SEG_MODE_FNC1_FIRST, SEG_MODE_FNC1_SECOND,
+};
+
+//payload and symbol parameters read back from a module matrix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoded {
+    pub version: Version,
+    pub err_level: ErrorLevel,
+    pub mask: u8,
+    pub eci: Option<Eci>,
+    pub fnc1: Option<Fnc1>,
+    pub append: Option<StructuredAppend>,
+    //numeric and alphanumeric segments as ASCII, byte segments as is, kanji as UTF-8
+    pub data: Vec<u8>,
+    pub corrected: usize, //code words fixed by error correction
+    text: Option<String>,
+}
+
+impl Decoded {
+    //byte segments are ISO-8859-1 without an ECI and UTF-8 under ECI 26, None for other ECIs
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+}
+
+// read format, unmask, de-interleave and correct the blocks, then parse the segments
+pub fn decode(matrix: &QrMatrix) -> Result<Decoded, EncodingErr> {
+    decode_with_erasures(matrix, |_| false)
+}
+
+// decode with the modules in erased, such as a logo or an unreadable region, marked unreliable
+//code words with an erased module are erasures, which cost half an error to correct
+pub fn decode_with_erasures(
+    matrix: &QrMatrix,
+    erased: impl Fn((u8, u8)) -> bool,
+) -> Result<Decoded, EncodingErr> {
+    let version = matrix.version();
+    if !version.is_valid() {
+        return Err(UnsupportedVersion(version.0));
+    }
+    let version = read_version(matrix)?;
+    let (err_level, mask) = read_format(matrix)?;
+    let (code_words, erasures) = read_code_words(matrix, mask, erased);
+    let (data_words, corrected) =
+        err_level.correct_code_words(version.0, &code_words, &erasures)?;
+    let mut decoded = Decoded {
+        version,
+        err_level,
+        mask,
+        eci: None,
+        fnc1: None,
+        append: None,
+        data: Vec::new(),
+        corrected,
+        text: Some(String::new()),
+    };
+    collect_segments(parse_segments(&data_words, version)?, &mut decoded);
+    Ok(decoded)
+}
+
+//level and mask of the format word nearest either copy, at most 3 bits apart
+fn read_format(matrix: &QrMatrix) -> Result<(ErrorLevel, u8), EncodingErr> {
+    //positions are the same for every level and mask, first 15 modules are the first copy
+    let modules = matrix.version().format_modules(ErrorLevel::L, 0)?;
+    let copy = |c: usize| {
+        modules[c * 15..(c + 1) * 15]
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, m)| {
+                let (x, y) = m.position();
+                acc | (u32::from(matrix.get(x, y)) << i)
+            })
+    };
+    ErrorLevel::nearest_format(&[copy(0), copy(1)]).ok_or(InvalidFormat)
+}
+
+//version from the two version info blocks, versions below 7 have none and use the symbol size
+fn read_version(matrix: &QrMatrix) -> Result<Version, EncodingErr> {
+    let version = matrix.version();
+    if !version.has_version_info() {
+        return Ok(version);
+    }
+    //modules alternate between the top right and bottom left blocks
+    let mut copies = [0u32; 2];
+    for (i, (x, y, _)) in version.version_info_iter().enumerate() {
+        copies[i % 2] |= u32::from(matrix.get(x, y)) << (i / 2);
+    }
+    match Version::nearest_version(&copies) {
+        Some(v) if v != version => Err(InvalidMatrix),
+        //both copies unreadable, the size still decides
+        _ => Ok(version),
+    }
+}
+
+//unmasked code words in placement order and indexes of those with an erased module
+//remainder bits are dropped
+fn read_code_words(
+    matrix: &QrMatrix,
+    mask: u8,
+    erased: impl Fn((u8, u8)) -> bool,
+) -> (Vec<u8>, Vec<usize>) {
+    let version = matrix.version();
+    let mut words = vec![0u8; version.code_words()];
+    let mut erasures = Vec::new();
+    let num_bits = words.len() * 8;
+    for (i, (x, y)) in version.data_region_iter().take(num_bits).enumerate() {
+        if matrix.get(x, y) != MASK_FN[mask as usize]((x, y)) {
+            words[i / 8] |= 0x80 >> (i % 8);
+        }
+        if erased((x, y)) && erasures.last() != Some(&(i / 8)) {
+            erasures.push(i / 8);
+        }
+    }
+    (words, erasures)
+}
+
+//segment read back from data code words, owned counterpart of Segment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParsedSegment {
+    Numeric(String),
+    Alphanumeric(String),
+    Bytes(Vec<u8>),
+    Kanji(String),
+    Eci(Eci),
+    Fnc1(Fnc1),
+    StructuredAppend(StructuredAppend),
+    Terminator, //end of data, the 4 bit terminator, a partial one or padding
+}
+
+impl ParsedSegment {
+    //segment that encodes to the same bits, None for the terminator
+    pub fn as_segment(&self) -> Option<Segment<'_>> {
+        match self {
+            ParsedSegment::Numeric(data) => Some(Segment::Numeric(data)),
+            ParsedSegment::Alphanumeric(data) => Some(Segment::Alphanumeric(data)),
+            ParsedSegment::Bytes(data) => Some(Segment::Bytes(data)),
+            ParsedSegment::Kanji(data) => Some(Segment::Kanji(data)),
+            ParsedSegment::Eci(eci) => Some(Segment::Eci(*eci)),
+            ParsedSegment::Fnc1(f) => Some(Segment::Fnc1(*f)),
+            ParsedSegment::StructuredAppend(append) => Some(Segment::StructuredAppend(*append)),
+            ParsedSegment::Terminator => None,
+        }
+    }
+}
+
+// parse corrected data code words of a symbol of version into segments, the inverse of encode_byte_segment
+//always ends with a terminator, which may be cut short or missing when data fills the symbol
+pub fn parse_segments(
+    data_words: &[u8],
+    version: Version,
+) -> Result<Vec<ParsedSegment>, EncodingErr> {
+    let mut r = BigEndianBitReader::new(data_words);
+    let mut segments = Vec::new();
+    while r.bits_remaining() >= 4 && !at_padding(&r, data_words) {
+        let indicator = read(&mut r, 4)? as u8;
+        let segment = match indicator {
+            0b0000 => break,
+            SEG_MODE_ECI => ParsedSegment::Eci(read_eci(&mut r)?),
+            SEG_MODE_FNC1_FIRST => ParsedSegment::Fnc1(Fnc1::First),
+            SEG_MODE_FNC1_SECOND => ParsedSegment::Fnc1(Fnc1::Second(read(&mut r, 8)? as u8)),
+            SEG_MODE_STRUCTURED_APPEND => ParsedSegment::StructuredAppend(StructuredAppend {
+                index: read(&mut r, 4)? as u8,
+                total: read(&mut r, 4)? as u8 + 1,
+                parity: read(&mut r, 8)? as u8,
+            }),
+            _ => {
+                let mode = Mode::from_indicator(indicator).ok_or(InvalidData)?;
+                let count = read(&mut r, mode.char_count_bits(version))? as usize;
+                let fnc1 = segments.iter().any(|s| matches!(s, ParsedSegment::Fnc1(_)));
+                read_data(&mut r, mode, count, fnc1)?
+            }
+        };
+        segments.push(segment);
+    }
+    segments.push(ParsedSegment::Terminator);
+    Ok(segments)
+}
+
+//fields of a data segment are whole, running out of bits means the data is corrupt
+fn read(r: &mut BigEndianBitReader, num_of_bits: u8) -> Result<u32, EncodingErr> {
+    r.read_bits(num_of_bits).map_err(|_| InvalidData)
+}
+
+//rest of the code words are 0xEC 0x11 padding with no terminator ahead of them
+fn at_padding(r: &BigEndianBitReader, data_words: &[u8]) -> bool {
+    const PAD_BYTES: [u8; 2] = [0xEC, 0x11];
+    let bit = r.bits_read();
+    bit.is_multiple_of(8)
+        && data_words[bit / 8..]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == PAD_BYTES[i & 1])
+}
+
+fn read_data(
+    r: &mut BigEndianBitReader,
+    mode: Mode,
+    count: usize,
+    fnc1: bool,
+) -> Result<ParsedSegment, EncodingErr> {
+    let segment = match mode {
+        Mode::Numeric => {
+            let mut digits = String::with_capacity(count);
+            for group in (0..count).step_by(3) {
+                let width = (count - group).min(3);
+                let value = read(r, [0, 4, 7, 10][width])?;
+                if value >= 10u32.pow(width as u32) {
+                    return Err(InvalidData);
+                }
+                digits.push_str(&format!("{value:0width$}"));
+            }
+            ParsedSegment::Numeric(digits)
+        }
+        Mode::Alphanumeric => {
+            let mut chars = Vec::with_capacity(count);
+            for pair in (0..count).step_by(2) {
+                if count - pair >= 2 {
+                    let value = read(r, 11)? as usize;
+                    chars.extend([value / 45, value % 45]);
+                } else {
+                    chars.push(read(r, 6)? as usize);
+                }
+            }
+            let mut text = String::with_capacity(count);
+            let mut chars = chars.into_iter().peekable();
+            while let Some(i) = chars.next() {
+                let ch = *ALPHANUMERIC_CHARS.get(i).ok_or(InvalidData)? as char;
+                //in FNC1 mode % is GS and %% a literal %
+                if ch == '%' && fnc1 {
+                    let is_pct = |i: &usize| ALPHANUMERIC_CHARS.get(*i) == Some(&b'%');
+                    text.push(if chars.next_if(is_pct).is_some() {
+                        '%'
+                    } else {
+                        '\u{1D}'
+                    });
+                } else {
+                    text.push(ch);
+                }
+            }
+            ParsedSegment::Alphanumeric(text)
+        }
+        Mode::Bytes => {
+            let bytes = (0..count)
+                .map(|_| read(r, 8).map(|b| b as u8))
+                .collect::<Result<Vec<u8>, _>>()?;
+            ParsedSegment::Bytes(bytes)
+        }
+        Mode::Kanji => {
+            let mut text = String::with_capacity(count * 3);
+            for _ in 0..count {
+                let value = read(r, 13)? as u16;
+                let code = ((value / 0xC0) << 8) | (value % 0xC0);
+                let code = if code < 0x1F00 {
+                    code + 0x8140
+                } else {
+                    code + 0xC140
+                };
+                text.push(sjis::from_shift_jis(code).ok_or(InvalidData)?);
+            }
+            ParsedSegment::Kanji(text)
+        }
+    };
+    Ok(segment)
+}
+
+//designator prefixed 0, 10 or 110 for 1, 2 or 3 bytes
+fn read_eci(r: &mut BigEndianBitReader) -> Result<Eci, EncodingErr> {
+    let first = read(r, 8)?;
+    let value = match (first as u8).leading_ones() {
+        0 => first,
+        1 => ((first & 0x3F) << 8) | read(r, 8)?,
+        2 => ((first & 0x1F) << 16) | read(r, 16)?,
+        _ => return Err(InvalidData),
+    };
+    Ok(Eci(value))
+}
+
+//header fields and payload of segments
+fn collect_segments(segments: Vec<ParsedSegment>, decoded: &mut Decoded) {
+    for segment in segments {
+        let data = &mut decoded.data;
+        match segment {
+            ParsedSegment::Eci(eci) => decoded.eci = Some(eci),
+            ParsedSegment::Fnc1(f) => decoded.fnc1 = Some(f),
+            ParsedSegment::StructuredAppend(append) => decoded.append = Some(append),
+            ParsedSegment::Numeric(text)
+            | ParsedSegment::Alphanumeric(text)
+            | ParsedSegment::Kanji(text) => {
+                data.extend_from_slice(text.as_bytes());
+                if let Some(decoded_text) = &mut decoded.text {
+                    decoded_text.push_str(&text);
+                }
+            }
+            ParsedSegment::Bytes(bytes) => {
+                let text = byte_text(&bytes, decoded.eci);
+                decoded.text = decoded.text.take().zip(text).map(|(t, b)| t + &b);
+                data.extend(bytes);
+            }
+            ParsedSegment::Terminator => break,
+        }
+    }
+}
+
+//ISO/IEC 18004 reads bytes as ISO-8859-1 until an ECI says otherwise, ECI 3 is also ISO-8859-1
+fn byte_text(bytes: &[u8], eci: Option<Eci>) -> Option<String> {
+    match eci {
+        None | Some(Eci(3)) => Some(bytes.iter().map(|b| char::from(*b)).collect()),
+        Some(Eci::UTF8) => String::from_utf8(bytes.to_vec()).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod decode_tests {
+    use crate::append::encode_structured_append;
+    use crate::append::StructuredAppend;
+    use crate::builder::QrBuilder;
+    use crate::decode::{decode, decode_with_erasures, parse_segments, ParsedSegment};
+    use crate::error_cc::ErrorLevel;
+    use crate::matrix::QrMatrix;
+    use crate::{
+        encode, encode_byte_segment, encode_fnc1, write_segment_list, Eci, EncodingErr, Fnc1,
+        Segment, Version,
+    };
+
+    #[test]
+    fn test_decode_round_trip() {
+        let data = [
+            "isaiah-perumalla",
+            "HELLO WORLD",
+            "01234567890123456789",
+            "order 123456789 点茗",
+            "µQRs é",
+        ];
+        for err_level in ErrorLevel::ALL {
+            for data in data {
+                let code = encode(data, err_level).unwrap();
+                let decoded = decode(&code.matrix().unwrap()).unwrap();
+                assert_eq!(decoded.text(), Some(data), "{err_level:?}");
+                assert_eq!(decoded.version, code.version);
+                assert_eq!(decoded.err_level, err_level);
+                assert_eq!(decoded.mask, code.mask);
+            }
+        }
+        let decoded = decode(&encode("µQRs é", ErrorLevel::L).unwrap().matrix().unwrap()).unwrap();
+        assert_eq!(decoded.eci, Some(Eci::UTF8));
+
+        //multiple blocks of two lengths and version info
+        let long = "lib-µQRs is tiny efficient Rust library to encode to QR code ".repeat(20);
+        let code = encode(&long, ErrorLevel::Q).unwrap();
+        assert!(code.version.0 > 20);
+        let decoded = decode(&code.matrix().unwrap()).unwrap();
+        assert_eq!(decoded.text(), Some(long.as_str()));
+    }
+
+    #[test]
+    fn test_decode_byte_charset() {
+        let decode_bytes = |eci: Option<Eci>, bytes: &[u8]| {
+            let mut builder = QrBuilder::new();
+            if let Some(eci) = eci {
+                builder.push(Segment::Eci(eci));
+            }
+            let code = builder.push(Segment::Bytes(bytes)).build().unwrap();
+            decode(&code.matrix().unwrap()).unwrap()
+        };
+        //without an ECI bytes are ISO-8859-1
+        let decoded = decode_bytes(None, b"caf\xE9");
+        assert_eq!(decoded.data, b"caf\xE9");
+        assert_eq!(decoded.text(), Some("café"));
+        assert_eq!(decode_bytes(None, "é".as_bytes()).text(), Some("Ã©"));
+        assert_eq!(decode_bytes(Some(Eci(3)), b"\xE9").text(), Some("é"));
+        //ECI 26 is UTF-8
+        assert_eq!(
+            decode_bytes(Some(Eci::UTF8), "é".as_bytes()).text(),
+            Some("é")
+        );
+        assert_eq!(decode_bytes(Some(Eci::UTF8), b"caf\xE9").text(), None);
+    }
+
+    #[test]
+    fn test_decode_headers() {
+        let code = encode_fnc1(
+            "01049123451234591597033130128\u{1D}10ABC%",
+            Fnc1::First,
+            ErrorLevel::M,
+        )
+        .unwrap();
+        let decoded = decode(&code.matrix().unwrap()).unwrap();
+        assert_eq!(decoded.fnc1, Some(Fnc1::First));
+        assert_eq!(
+            decoded.text(),
+            Some("01049123451234591597033130128\u{1D}10ABC%")
+        );
+
+        let code = QrBuilder::new()
+            .push(Segment::Eci(Eci(20_000)))
+            .push(Segment::Fnc1(Fnc1::Second(37)))
+            .push(Segment::Bytes(&[0xFF, 0x00, 0x80]))
+            .build()
+            .unwrap();
+        let decoded = decode(&code.matrix().unwrap()).unwrap();
+        assert_eq!(decoded.eci, Some(Eci(20_000)));
+        assert_eq!(decoded.fnc1, Some(Fnc1::Second(37)));
+        assert_eq!(decoded.data, [0xFF, 0x00, 0x80]);
+        assert_eq!(decoded.text(), None);
+
+        let data = "wifi:ssid=warehouse-7;psk=correct horse battery staple";
+        let codes = encode_structured_append(data, ErrorLevel::M, Version(1)).unwrap();
+        let mut message = Vec::new();
+        for (i, code) in codes.iter().enumerate() {
+            let decoded = decode(&code.matrix().unwrap()).unwrap();
+            let append = decoded.append.unwrap();
+            assert_eq!(
+                (append.index as usize, append.total as usize),
+                (i, codes.len())
+            );
+            message.extend_from_slice(&decoded.data);
+        }
+        assert_eq!(message, data.as_bytes());
+    }
+
+    #[test]
+    fn test_decode_text_dump() {
+        let code = encode("isaiah-perumalla", ErrorLevel::H).unwrap();
+        let dump = code.matrix().unwrap().to_string();
+        let matrix = QrMatrix::from_text(&dump).unwrap();
+        assert_eq!(matrix, code.matrix().unwrap());
+        assert_eq!(decode(&matrix).unwrap().text(), Some("isaiah-perumalla"));
+    }
+
+    #[test]
+    fn test_decode_format_errors() {
+        let code = encode("isaiah-perumalla", ErrorLevel::Q).unwrap();
+        let matrix = code.matrix().unwrap();
+        let size = matrix.size() as usize;
+        let format = code.version.format_modules(ErrorLevel::L, 0).unwrap();
+        let with_format = |words: [u32; 2]| {
+            let mut modules = matrix.to_bools();
+            for (i, m) in format.iter().enumerate() {
+                let (x, y) = m.position();
+                modules[y as usize * size + x as usize] = 0 != words[i / 15] & (1 << (i % 15));
+            }
+            QrMatrix::from_bools(size, &modules).unwrap()
+        };
+        //more than 3 bits from every format word
+        let unreadable = (0..1u32 << 15)
+            .find(|w| {
+                ErrorLevel::ALL.iter().all(|level| {
+                    (0..8).all(|mask| (w ^ level.format_bits(mask).unwrap()).count_ones() > 3)
+                })
+            })
+            .unwrap();
+        let format_word = ErrorLevel::Q.format_bits(code.mask).unwrap();
+
+        let damaged = with_format([format_word ^ 0b100_0010_0000_0001, unreadable]);
+        let decoded = decode(&damaged).unwrap();
+        assert_eq!(
+            (decoded.err_level, decoded.mask),
+            (ErrorLevel::Q, code.mask)
+        );
+        let damaged = with_format([unreadable, format_word ^ 0b11]);
+        assert_eq!(decode(&damaged).unwrap().err_level, ErrorLevel::Q);
+
+        let damaged = with_format([unreadable, unreadable]);
+        assert!(matches!(decode(&damaged), Err(EncodingErr::InvalidFormat)));
+    }
+
+    #[test]
+    fn test_decode_version_info() {
+        let data = "https://example.com/inventory/items?id=000123456789&utm_source=label&utm_medium=print&utm_campaign=warehouse-totes-2024";
+        let code = encode(data, ErrorLevel::M).unwrap();
+        assert_eq!(code.version, Version(7));
+        let matrix = code.matrix().unwrap();
+        let size = matrix.size() as usize;
+        let with_version_info = |words: [u32; 2]| {
+            let mut modules = matrix.to_bools();
+            for (i, (x, y, _)) in code.version.version_info_iter().enumerate() {
+                modules[y as usize * size + x as usize] = 0 != words[i % 2] & (1 << (i / 2));
+            }
+            QrMatrix::from_bools(size, &modules).unwrap()
+        };
+        let bits = Version(7).version_info_bits();
+        let damaged = with_version_info([bits ^ 0b111, !bits & 0x3FFFF]);
+        assert_eq!(decode(&damaged).unwrap().text(), Some(data));
+        //unreadable version info falls back to the symbol size
+        let damaged = with_version_info([0x3FFFF, 0]);
+        assert_eq!(decode(&damaged).unwrap().version, Version(7));
+        let damaged = with_version_info([Version(9).version_info_bits(), bits ^ 0b1111]);
+        assert!(matches!(decode(&damaged), Err(EncodingErr::InvalidMatrix)));
+    }
+
+    #[test]
+    fn test_parse_byte_segment() {
+        let mut out = [0u8; 19];
+        let n = encode_byte_segment("hello", &mut out).unwrap();
+        let segments = parse_segments(&out[..n], Version(1)).unwrap();
+        assert_eq!(
+            segments,
+            [
+                ParsedSegment::Bytes(b"hello".to_vec()),
+                ParsedSegment::Terminator
+            ]
+        );
+        //padding after the terminator
+        crate::add_padding(&mut out[n..]);
+        assert_eq!(parse_segments(&out, Version(1)).unwrap(), segments);
+        //padding with no terminator ahead of it, byte segment ends on a byte boundary
+        let mut words = vec![0x40, 0x26, 0x86, 0x90];
+        words.extend([0xEC, 0x11, 0xEC]);
+        assert_eq!(
+            parse_segments(&words, Version(1)).unwrap(),
+            [
+                ParsedSegment::Bytes(b"hi".to_vec()),
+                ParsedSegment::Terminator
+            ]
+        );
+
+        let segments = parse_segments(&[0xEC, 0x11], Version(1)).unwrap();
+        assert_eq!(segments, [ParsedSegment::Terminator]);
+        assert_eq!(
+            parse_segments(&[], Version(1)).unwrap(),
+            [ParsedSegment::Terminator]
+        );
+    }
+
+    #[test]
+    fn test_parse_all_segment_types() {
+        let append = StructuredAppend {
+            index: 2,
+            total: 4,
+            parity: 0x5A,
+        };
+        let written = [
+            Segment::StructuredAppend(append),
+            Segment::Fnc1(Fnc1::Second(37)),
+            Segment::Eci(Eci(20000)),
+            Segment::Numeric("0123456"),
+            Segment::Alphanumeric("AC-42%%"),
+            Segment::Bytes(&[0x00, 0xFF, 0x80]),
+            Segment::Kanji("茗荷"),
+        ];
+        for version in [Version(1), Version(10), Version(27)] {
+            let mut out = [0u8; 64];
+            let n = write_segment_list(&written, version, &mut out).unwrap();
+            let segments = parse_segments(&out[..n], version).unwrap();
+            assert_eq!(segments.last(), Some(&ParsedSegment::Terminator));
+            let read: Vec<Segment> = segments.iter().filter_map(|s| s.as_segment()).collect();
+            assert_eq!(read, written, "version {}", version.0);
+        }
+    }
+
+    #[test]
+    fn test_parse_partial_terminator() {
+        //numeric 0123456 in 1-L is 4+10+10+10+4 = 38 bits, a full terminator takes 6 bytes
+        let mut out = [0u8; 6];
+        let n = write_segment_list(&[Segment::Numeric("0123456")], Version(1), &mut out).unwrap();
+        assert_eq!(n, 6);
+        //in 5 bytes only 2 bits of terminator fit after the data
+        assert_eq!(out[..5], [0x10, 0x1C, 0x0C, 0x56, 0x58]);
+        assert_eq!(
+            parse_segments(&out[..5], Version(1)).unwrap(),
+            [
+                ParsedSegment::Numeric("0123456".to_string()),
+                ParsedSegment::Terminator
+            ]
+        );
+        //count past the end of the data
+        let truncated = parse_segments(&out[..4], Version(1));
+        assert!(matches!(truncated, Err(EncodingErr::InvalidData)));
+        //mode indicator 0110 is not used
+        assert!(matches!(
+            parse_segments(&[0x60], Version(1)),
+            Err(EncodingErr::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_decode_corrects_errors() {
+        let code = encode("isaiah-perumalla", ErrorLevel::L).unwrap();
+        let mut modules = code.matrix().unwrap().to_bools();
+        //bottom right corner holds the first data code word
+        modules[21 * 21 - 1] = !modules[21 * 21 - 1];
+        let damaged = QrMatrix::from_bools(21, &modules).unwrap();
+        let decoded = decode(&damaged).unwrap();
+        assert_eq!(decoded.text(), Some("isaiah-perumalla"));
+        assert_eq!(decoded.corrected, 1);
+        assert_eq!(decode(&code.matrix().unwrap()).unwrap().corrected, 0);
+
+        //7 ecc words of 1-L fix 3 words, a 4x4 square covers 4 or more
+        for (x, y) in (11..15).flat_map(|y| (11..15).map(move |x| (x, y))) {
+            modules[y * 21 + x] = !modules[y * 21 + x];
+        }
+        let damaged = QrMatrix::from_bools(21, &modules).unwrap();
+        assert!(matches!(decode(&damaged), Err(EncodingErr::TooManyErrors)));
+
+        //17 ecc words of 1-H fix 8 words
+        let code = encode("isaiah", ErrorLevel::H).unwrap();
+        let mut modules = code.matrix().unwrap().to_bools();
+        for (x, y) in (9..14).flat_map(|y| (9..14).map(move |x| (x, y))) {
+            modules[y * 21 + x] = !modules[y * 21 + x];
+        }
+        let damaged = QrMatrix::from_bools(21, &modules).unwrap();
+        let decoded = decode(&damaged).unwrap();
+        assert_eq!(decoded.text(), Some("isaiah"));
+        assert!(decoded.corrected > 3);
+    }
+
+    #[test]
+    fn test_decode_with_erasures() {
+        //logo over the centre of a 3-H symbol, 2 blocks with 22 ecc words each
+        let data = "https://example.com/qr";
+        let code = encode(data, ErrorLevel::H).unwrap();
+        assert_eq!(code.version.0, 3);
+        let logo = |(x, y): (u8, u8)| (9..21).contains(&x) && (4..24).contains(&y);
+        let mut modules = code.matrix().unwrap().to_bools();
+        for y in 0..29u8 {
+            for x in 0..29u8 {
+                if logo((x, y)) {
+                    modules[y as usize * 29 + x as usize] = false;
+                }
+            }
+        }
+        let covered = QrMatrix::from_bools(29, &modules).unwrap();
+        assert!(matches!(decode(&covered), Err(EncodingErr::TooManyErrors)));
+        let decoded = decode_with_erasures(&covered, logo).unwrap();
+        assert_eq!(decoded.text(), Some(data));
+        assert!(decoded.corrected > 22);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::gf256;
 use crate::gf256::{gen_polynomial, Term};
+use crate::EncodingErr::{BufferTooSmall, DataTooLong, InvalidMask, UnsupportedVersion};
+use crate::{EncodingErr, Version};
 
 #[derive(Clone, Copy, Debug)]
 struct DataCapacity {
@@ -11,74 +13,231 @@ struct DataCapacity {
 }
 
 impl DataCapacity {
+    const fn new(
+        ec_words_per_blk: u8,
+        grp_1_blks: u8,
+        words_per_grp_1: u8,
+        grp_2_blks: u8,
+        words_per_grp_2: u8,
+    ) -> DataCapacity {
+        DataCapacity {
+            ec_words_per_blk,
+            grp_1_blks,
+            words_per_grp_1,
+            grp_2_blks,
+            words_per_grp_2,
+        }
+    }
+
     fn total_data_words(&self) -> usize {
         let words_grp_1 = self.words_per_grp_1 as u16 * (self.grp_1_blks as u16);
         let words_grp_2 = self.words_per_grp_2 as u16 * (self.grp_2_blks as u16);
         (words_grp_1 + words_grp_2) as usize
     }
+
+    fn total_blocks(&self) -> usize {
+        (self.grp_1_blks + self.grp_2_blks) as usize
+    }
+
+    //number of data words in block, group 1 blocks come first
+    fn block_len(&self, block: usize) -> usize {
+        if block < self.grp_1_blks as usize {
+            self.words_per_grp_1 as usize
+        } else {
+            self.words_per_grp_2 as usize
+        }
+    }
+
+    fn total_ec_words(&self) -> usize {
+        self.ec_words_per_blk as usize * self.total_blocks()
+    }
 }
 
-const DATA_CAPACITY_L: [DataCapacity; 8] = [
-    DataCapacity {
-        ec_words_per_blk: 0,
-        grp_1_blks: 0,
-        words_per_grp_1: 0,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    },
-    DataCapacity {
-        ec_words_per_blk: 7,
-        grp_1_blks: 1,
-        words_per_grp_1: 19,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    }, //v1
-    DataCapacity {
-        ec_words_per_blk: 10,
-        grp_1_blks: 1,
-        words_per_grp_1: 34,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    },
-    DataCapacity {
-        ec_words_per_blk: 15,
-        grp_1_blks: 1,
-        words_per_grp_1: 55,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    },
-    DataCapacity {
-        ec_words_per_blk: 20,
-        grp_1_blks: 1,
-        words_per_grp_1: 80,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    }, //v4
-    DataCapacity {
-        ec_words_per_blk: 26,
-        grp_1_blks: 1,
-        words_per_grp_1: 108,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    }, //v5
-    DataCapacity {
-        ec_words_per_blk: 18,
-        grp_1_blks: 2,
-        words_per_grp_1: 68,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    }, //v6
-    DataCapacity {
-        ec_words_per_blk: 20,
-        grp_1_blks: 2,
-        words_per_grp_1: 78,
-        grp_2_blks: 0,
-        words_per_grp_2: 0,
-    }, //v7
+//largest data and ecc word counts across all versions and levels (version 40)
+const MAX_DATA_WORDS: usize = 2956;
+const MAX_EC_WORDS: usize = 2430;
+
+//ec words and block structure per version, index 0 unused
+const DATA_CAPACITY_L: [DataCapacity; 41] = [
+    DataCapacity::new(0, 0, 0, 0, 0),
+    DataCapacity::new(7, 1, 19, 0, 0),      //v1
+    DataCapacity::new(10, 1, 34, 0, 0),     //v2
+    DataCapacity::new(15, 1, 55, 0, 0),     //v3
+    DataCapacity::new(20, 1, 80, 0, 0),     //v4
+    DataCapacity::new(26, 1, 108, 0, 0),    //v5
+    DataCapacity::new(18, 2, 68, 0, 0),     //v6
+    DataCapacity::new(20, 2, 78, 0, 0),     //v7
+    DataCapacity::new(24, 2, 97, 0, 0),     //v8
+    DataCapacity::new(30, 2, 116, 0, 0),    //v9
+    DataCapacity::new(18, 2, 68, 2, 69),    //v10
+    DataCapacity::new(20, 4, 81, 0, 0),     //v11
+    DataCapacity::new(24, 2, 92, 2, 93),    //v12
+    DataCapacity::new(26, 4, 107, 0, 0),    //v13
+    DataCapacity::new(30, 3, 115, 1, 116),  //v14
+    DataCapacity::new(22, 5, 87, 1, 88),    //v15
+    DataCapacity::new(24, 5, 98, 1, 99),    //v16
+    DataCapacity::new(28, 1, 107, 5, 108),  //v17
+    DataCapacity::new(30, 5, 120, 1, 121),  //v18
+    DataCapacity::new(28, 3, 113, 4, 114),  //v19
+    DataCapacity::new(28, 3, 107, 5, 108),  //v20
+    DataCapacity::new(28, 4, 116, 4, 117),  //v21
+    DataCapacity::new(28, 2, 111, 7, 112),  //v22
+    DataCapacity::new(30, 4, 121, 5, 122),  //v23
+    DataCapacity::new(30, 6, 117, 4, 118),  //v24
+    DataCapacity::new(26, 8, 106, 4, 107),  //v25
+    DataCapacity::new(28, 10, 114, 2, 115), //v26
+    DataCapacity::new(30, 8, 122, 4, 123),  //v27
+    DataCapacity::new(30, 3, 117, 10, 118), //v28
+    DataCapacity::new(30, 7, 116, 7, 117),  //v29
+    DataCapacity::new(30, 5, 115, 10, 116), //v30
+    DataCapacity::new(30, 13, 115, 3, 116), //v31
+    DataCapacity::new(30, 17, 115, 0, 0),   //v32
+    DataCapacity::new(30, 17, 115, 1, 116), //v33
+    DataCapacity::new(30, 13, 115, 6, 116), //v34
+    DataCapacity::new(30, 12, 121, 7, 122), //v35
+    DataCapacity::new(30, 6, 121, 14, 122), //v36
+    DataCapacity::new(30, 17, 122, 4, 123), //v37
+    DataCapacity::new(30, 4, 122, 18, 123), //v38
+    DataCapacity::new(30, 20, 117, 4, 118), //v39
+    DataCapacity::new(30, 19, 118, 6, 119), //v40
+];
+
+const DATA_CAPACITY_M: [DataCapacity; 41] = [
+    DataCapacity::new(0, 0, 0, 0, 0),
+    DataCapacity::new(10, 1, 16, 0, 0),    //v1
+    DataCapacity::new(16, 1, 28, 0, 0),    //v2
+    DataCapacity::new(26, 1, 44, 0, 0),    //v3
+    DataCapacity::new(18, 2, 32, 0, 0),    //v4
+    DataCapacity::new(24, 2, 43, 0, 0),    //v5
+    DataCapacity::new(16, 4, 27, 0, 0),    //v6
+    DataCapacity::new(18, 4, 31, 0, 0),    //v7
+    DataCapacity::new(22, 2, 38, 2, 39),   //v8
+    DataCapacity::new(22, 3, 36, 2, 37),   //v9
+    DataCapacity::new(26, 4, 43, 1, 44),   //v10
+    DataCapacity::new(30, 1, 50, 4, 51),   //v11
+    DataCapacity::new(22, 6, 36, 2, 37),   //v12
+    DataCapacity::new(22, 8, 37, 1, 38),   //v13
+    DataCapacity::new(24, 4, 40, 5, 41),   //v14
+    DataCapacity::new(24, 5, 41, 5, 42),   //v15
+    DataCapacity::new(28, 7, 45, 3, 46),   //v16
+    DataCapacity::new(28, 10, 46, 1, 47),  //v17
+    DataCapacity::new(26, 9, 43, 4, 44),   //v18
+    DataCapacity::new(26, 3, 44, 11, 45),  //v19
+    DataCapacity::new(26, 3, 41, 13, 42),  //v20
+    DataCapacity::new(26, 17, 42, 0, 0),   //v21
+    DataCapacity::new(28, 17, 46, 0, 0),   //v22
+    DataCapacity::new(28, 4, 47, 14, 48),  //v23
+    DataCapacity::new(28, 6, 45, 14, 46),  //v24
+    DataCapacity::new(28, 8, 47, 13, 48),  //v25
+    DataCapacity::new(28, 19, 46, 4, 47),  //v26
+    DataCapacity::new(28, 22, 45, 3, 46),  //v27
+    DataCapacity::new(28, 3, 45, 23, 46),  //v28
+    DataCapacity::new(28, 21, 45, 7, 46),  //v29
+    DataCapacity::new(28, 19, 47, 10, 48), //v30
+    DataCapacity::new(28, 2, 46, 29, 47),  //v31
+    DataCapacity::new(28, 10, 46, 23, 47), //v32
+    DataCapacity::new(28, 14, 46, 21, 47), //v33
+    DataCapacity::new(28, 14, 46, 23, 47), //v34
+    DataCapacity::new(28, 12, 47, 26, 48), //v35
+    DataCapacity::new(28, 6, 47, 34, 48),  //v36
+    DataCapacity::new(28, 29, 46, 14, 47), //v37
+    DataCapacity::new(28, 13, 46, 32, 47), //v38
+    DataCapacity::new(28, 40, 47, 7, 48),  //v39
+    DataCapacity::new(28, 18, 47, 31, 48), //v40
+];
+
+const DATA_CAPACITY_Q: [DataCapacity; 41] = [
+    DataCapacity::new(0, 0, 0, 0, 0),
+    DataCapacity::new(13, 1, 13, 0, 0),    //v1
+    DataCapacity::new(22, 1, 22, 0, 0),    //v2
+    DataCapacity::new(18, 2, 17, 0, 0),    //v3
+    DataCapacity::new(26, 2, 24, 0, 0),    //v4
+    DataCapacity::new(18, 2, 15, 2, 16),   //v5
+    DataCapacity::new(24, 4, 19, 0, 0),    //v6
+    DataCapacity::new(18, 2, 14, 4, 15),   //v7
+    DataCapacity::new(22, 4, 18, 2, 19),   //v8
+    DataCapacity::new(20, 4, 16, 4, 17),   //v9
+    DataCapacity::new(24, 6, 19, 2, 20),   //v10
+    DataCapacity::new(28, 4, 22, 4, 23),   //v11
+    DataCapacity::new(26, 4, 20, 6, 21),   //v12
+    DataCapacity::new(24, 8, 20, 4, 21),   //v13
+    DataCapacity::new(20, 11, 16, 5, 17),  //v14
+    DataCapacity::new(30, 5, 24, 7, 25),   //v15
+    DataCapacity::new(24, 15, 19, 2, 20),  //v16
+    DataCapacity::new(28, 1, 22, 15, 23),  //v17
+    DataCapacity::new(28, 17, 22, 1, 23),  //v18
+    DataCapacity::new(26, 17, 21, 4, 22),  //v19
+    DataCapacity::new(30, 15, 24, 5, 25),  //v20
+    DataCapacity::new(28, 17, 22, 6, 23),  //v21
+    DataCapacity::new(30, 7, 24, 16, 25),  //v22
+    DataCapacity::new(30, 11, 24, 14, 25), //v23
+    DataCapacity::new(30, 11, 24, 16, 25), //v24
+    DataCapacity::new(30, 7, 24, 22, 25),  //v25
+    DataCapacity::new(28, 28, 22, 6, 23),  //v26
+    DataCapacity::new(30, 8, 23, 26, 24),  //v27
+    DataCapacity::new(30, 4, 24, 31, 25),  //v28
+    DataCapacity::new(30, 1, 23, 37, 24),  //v29
+    DataCapacity::new(30, 15, 24, 25, 25), //v30
+    DataCapacity::new(30, 42, 24, 1, 25),  //v31
+    DataCapacity::new(30, 10, 24, 35, 25), //v32
+    DataCapacity::new(30, 29, 24, 19, 25), //v33
+    DataCapacity::new(30, 44, 24, 7, 25),  //v34
+    DataCapacity::new(30, 39, 24, 14, 25), //v35
+    DataCapacity::new(30, 46, 24, 10, 25), //v36
+    DataCapacity::new(30, 49, 24, 10, 25), //v37
+    DataCapacity::new(30, 48, 24, 14, 25), //v38
+    DataCapacity::new(30, 43, 24, 22, 25), //v39
+    DataCapacity::new(30, 34, 24, 34, 25), //v40
 ];
 
-#[derive(Clone, Copy)]
+const DATA_CAPACITY_H: [DataCapacity; 41] = [
+    DataCapacity::new(0, 0, 0, 0, 0),
+    DataCapacity::new(17, 1, 9, 0, 0),     //v1
+    DataCapacity::new(28, 1, 16, 0, 0),    //v2
+    DataCapacity::new(22, 2, 13, 0, 0),    //v3
+    DataCapacity::new(16, 4, 9, 0, 0),     //v4
+    DataCapacity::new(22, 2, 11, 2, 12),   //v5
+    DataCapacity::new(28, 4, 15, 0, 0),    //v6
+    DataCapacity::new(26, 4, 13, 1, 14),   //v7
+    DataCapacity::new(26, 4, 14, 2, 15),   //v8
+    DataCapacity::new(24, 4, 12, 4, 13),   //v9
+    DataCapacity::new(28, 6, 15, 2, 16),   //v10
+    DataCapacity::new(24, 3, 12, 8, 13),   //v11
+    DataCapacity::new(28, 7, 14, 4, 15),   //v12
+    DataCapacity::new(22, 12, 11, 4, 12),  //v13
+    DataCapacity::new(24, 11, 12, 5, 13),  //v14
+    DataCapacity::new(24, 11, 12, 7, 13),  //v15
+    DataCapacity::new(30, 3, 15, 13, 16),  //v16
+    DataCapacity::new(28, 2, 14, 17, 15),  //v17
+    DataCapacity::new(28, 2, 14, 19, 15),  //v18
+    DataCapacity::new(26, 9, 13, 16, 14),  //v19
+    DataCapacity::new(28, 15, 15, 10, 16), //v20
+    DataCapacity::new(30, 19, 16, 6, 17),  //v21
+    DataCapacity::new(24, 34, 13, 0, 0),   //v22
+    DataCapacity::new(30, 16, 15, 14, 16), //v23
+    DataCapacity::new(30, 30, 16, 2, 17),  //v24
+    DataCapacity::new(30, 22, 15, 13, 16), //v25
+    DataCapacity::new(30, 33, 16, 4, 17),  //v26
+    DataCapacity::new(30, 12, 15, 28, 16), //v27
+    DataCapacity::new(30, 11, 15, 31, 16), //v28
+    DataCapacity::new(30, 19, 15, 26, 16), //v29
+    DataCapacity::new(30, 23, 15, 25, 16), //v30
+    DataCapacity::new(30, 23, 15, 28, 16), //v31
+    DataCapacity::new(30, 19, 15, 35, 16), //v32
+    DataCapacity::new(30, 11, 15, 46, 16), //v33
+    DataCapacity::new(30, 59, 16, 1, 17),  //v34
+    DataCapacity::new(30, 22, 15, 41, 16), //v35
+    DataCapacity::new(30, 2, 15, 64, 16),  //v36
+    DataCapacity::new(30, 24, 15, 46, 16), //v37
+    DataCapacity::new(30, 42, 15, 32, 16), //v38
+    DataCapacity::new(30, 10, 15, 67, 16), //v39
+    DataCapacity::new(30, 20, 15, 61, 16), //v40
+];
+
+//ordered by recovery capacity, L lowest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ErrorLevel {
+    #[default]
     L,
     M,
     Q,
@@ -86,97 +245,529 @@ pub enum ErrorLevel {
 }
 
 impl ErrorLevel {
-    pub fn format_bits(&self, mask: u8) -> u32 {
-        let l_mask_pattern: [u32; 8] = [
-            0b111011111000100,
-            0b111001011110011,
-            0b111110110101010,
-            0b111100010011101,
-            0b110011000101111,
-            0b110001100011000,
-            0b110110001000001,
-            0b110100101110110,
-        ];
-        match (*self, mask) {
-            (ErrorLevel::L, m) => l_mask_pattern[m as usize],
-            _ => 0,
+    pub const ALL: [ErrorLevel; 4] = [ErrorLevel::L, ErrorLevel::M, ErrorLevel::Q, ErrorLevel::H];
+
+    //highest level from self up that still holds data_bits in version
+    pub fn boosted(&self, version: u8, data_bits: usize) -> ErrorLevel {
+        ErrorLevel::ALL
+            .into_iter()
+            .rev()
+            .find(|level| level >= self && level.data_bits(version) >= data_bits)
+            .unwrap_or(*self)
+    }
+
+    //2 level bits and 3 mask bits with BCH(15,5) error correction bits, xor 0x5412 so no word is all zero
+    pub fn format_bits(&self, mask: u8) -> Result<u32, EncodingErr> {
+        if mask > 7 {
+            return Err(InvalidMask);
+        }
+        const GENERATOR: u32 = 0x537;
+        let level = match *self {
+            ErrorLevel::L => 0b01,
+            ErrorLevel::M => 0b00,
+            ErrorLevel::Q => 0b11,
+            ErrorLevel::H => 0b10,
+        };
+        let data = (level << 3) | mask as u32;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * GENERATOR);
         }
+        Ok(((data << 10) | rem) ^ 0x5412)
+    }
+
+    //level and mask of the format word nearest any of copies, None when more than 3 bits differ
+    pub fn nearest_format(copies: &[u32]) -> Option<(ErrorLevel, u8)> {
+        let mut best = (u32::MAX, ErrorLevel::L, 0);
+        for err_level in ErrorLevel::ALL {
+            for mask in 0..8 {
+                let format = err_level.format_bits(mask).ok()?;
+                for copy in copies {
+                    let distance = (copy ^ format).count_ones();
+                    if distance < best.0 {
+                        best = (distance, err_level, mask);
+                    }
+                }
+            }
+        }
+        let (distance, err_level, mask) = best;
+        (distance <= 3).then_some((err_level, mask))
+    }
+
+    //zero capacity for versions outside 1 to 40
+    fn capacity(&self, version: u8) -> DataCapacity {
+        let table = match *self {
+            ErrorLevel::L => &DATA_CAPACITY_L,
+            ErrorLevel::M => &DATA_CAPACITY_M,
+            ErrorLevel::Q => &DATA_CAPACITY_Q,
+            ErrorLevel::H => &DATA_CAPACITY_H,
+        };
+        table.get(version as usize).copied().unwrap_or(table[0])
     }
 
     fn get_ecc_gf_poly(&self, version: u8) -> gf256::Poly {
-        let ecc_size = DATA_CAPACITY_L[version as usize].ec_words_per_blk;
+        let ecc_size = self.capacity(version).ec_words_per_blk;
         gen_polynomial(ecc_size)
     }
 
-    pub fn compute_ecc(&self, version: u8, block_data: &[u8], ecc_buffer: &mut [u8]) -> usize {
-        let data_poly = gf256::Poly::from((block_data.len() - 1) as u8, block_data);
+    //ecc words of one block of version, returns the number written to ecc_buffer
+    pub fn compute_ecc(
+        &self,
+        version: u8,
+        block_data: &[u8],
+        ecc_buffer: &mut [u8],
+    ) -> Result<usize, EncodingErr> {
+        if !Version(version).is_valid() {
+            return Err(UnsupportedVersion(version));
+        }
         let divisor = self.get_ecc_gf_poly(version);
-        let data_p = data_poly.multiply(Term(divisor.degree, 1));
-        let remainder = data_p.div_remainder(&divisor);
-        for (i, term) in remainder.terms().enumerate() {
-            ecc_buffer[i] = term.coef();
-        }
-        // debug_assert!(ecc_size, (remainder. degree + 1));
-        return (remainder.degree + 1) as usize;
-    }
-
-    pub fn add_error_codes(&self, version: u8, msg_buffer: &mut [u8]) -> usize {
-        match *self {
-            ErrorLevel::L => {
-                let capacity_info = DATA_CAPACITY_L[version as usize];
-                let data_size = capacity_info.total_data_words();
-                let mut ecc_words = [0u8; 32];
-                let ecc_size =
-                    ErrorLevel::L.compute_ecc(version, &msg_buffer[0..data_size], &mut ecc_words);
-                debug_assert!(
-                    data_size + ecc_size < msg_buffer.len(),
-                    "msg_buffer#len not big enough"
-                );
-                let ecc_blk = &ecc_words[0..ecc_size];
-                debug_assert!(
-                    ecc_size == capacity_info.ec_words_per_blk as usize,
-                    "ecc words per blk did not match "
-                );
-                for (i, byte) in ecc_blk.iter().enumerate() {
-                    msg_buffer[data_size + i] = *byte;
+        block_ecc(&divisor, block_data, ecc_buffer)
+    }
+
+    //msg_buffer holds data code words on input,
+    //on return holds data and ecc words of every block interleaved as placed in the symbol
+    pub fn add_error_codes(
+        &self,
+        version: u8,
+        msg_buffer: &mut [u8],
+    ) -> Result<usize, EncodingErr> {
+        if !Version(version).is_valid() {
+            return Err(UnsupportedVersion(version));
+        }
+        let capacity_info = self.capacity(version);
+        let data_size = capacity_info.total_data_words();
+        let total_size = data_size + capacity_info.total_ec_words();
+        if msg_buffer.len() < total_size {
+            return Err(BufferTooSmall {
+                needed_bytes: total_size,
+                available_bytes: msg_buffer.len(),
+            });
+        }
+        let mut data_words = [0u8; MAX_DATA_WORDS];
+        data_words[0..data_size].copy_from_slice(&msg_buffer[0..data_size]);
+
+        let ec_per_blk = capacity_info.ec_words_per_blk as usize;
+        let num_blocks = capacity_info.total_blocks();
+        let divisor = self.get_ecc_gf_poly(version);
+        let mut ecc_words = [0u8; MAX_EC_WORDS];
+        let mut blk_start = 0;
+        for b in 0..num_blocks {
+            let blk_len = capacity_info.block_len(b);
+            let ecc_blk = &mut ecc_words[(b * ec_per_blk)..((b + 1) * ec_per_blk)];
+            let ecc_size = block_ecc(
+                &divisor,
+                &data_words[blk_start..(blk_start + blk_len)],
+                ecc_blk,
+            )?;
+            debug_assert!(ecc_size == ec_per_blk, "ecc words per blk did not match ");
+            blk_start += blk_len;
+        }
+
+        //interleave, take i-th word of each block in turn, group 2 blocks are one word longer
+        let mut index = 0;
+        let max_blk_len = capacity_info.block_len(num_blocks - 1);
+        for i in 0..max_blk_len {
+            let mut blk_start = 0;
+            for b in 0..num_blocks {
+                let blk_len = capacity_info.block_len(b);
+                if i < blk_len {
+                    msg_buffer[index] = data_words[blk_start + i];
+                    index += 1;
                 }
-                (data_size + ecc_blk.len()) as usize
+                blk_start += blk_len;
             }
-            _ => todo!("only ErrorLevel L implemented"),
         }
+        for i in 0..ec_per_blk {
+            for b in 0..num_blocks {
+                msg_buffer[index] = ecc_words[b * ec_per_blk + i];
+                index += 1;
+            }
+        }
+        debug_assert!(index == total_size);
+        Ok(index)
     }
 
     pub fn total_words(&self, v: u8) -> usize {
-        match self {
-            ErrorLevel::L => {
-                let v = v as usize;
-                DATA_CAPACITY_L[v].total_data_words() + DATA_CAPACITY_L[v].ec_words_per_blk as usize
+        let capacity_info = self.capacity(v);
+        capacity_info.total_data_words() + capacity_info.total_ec_words()
+    }
+
+    pub fn data_code_words(&self, version: u8) -> usize {
+        self.capacity(version).total_data_words()
+    }
+
+    pub fn data_bits(&self, version: u8) -> usize {
+        self.data_code_words(version) * 8
+    }
+
+    pub fn block_count(&self, version: u8) -> usize {
+        self.capacity(version).total_blocks()
+    }
+
+    pub fn ec_words_per_block(&self, version: u8) -> usize {
+        self.capacity(version).ec_words_per_blk as usize
+    }
+
+    //correct a de-interleaved block of data words followed by its ecc words
+    //erasures are indexes of block words known to be unreadable, returns the number of corrected words
+    pub fn correct_block(
+        &self,
+        version: u8,
+        block: &mut [u8],
+        erasures: &[usize],
+    ) -> Result<usize, EncodingErr> {
+        gf256::correct_errors_and_erasures(block, self.ec_words_per_block(version), erasures)
+    }
+
+    //data words of every block in order and the number of corrected words
+    //code_words are interleaved as written by add_error_codes, erasures index into them
+    pub fn correct_code_words(
+        &self,
+        version: u8,
+        code_words: &[u8],
+        erasures: &[usize],
+    ) -> Result<(Vec<u8>, usize), EncodingErr> {
+        let mut blocks = vec![(Vec::new(), Vec::new()); self.block_count(version)];
+        for (i, word) in code_words.iter().enumerate() {
+            let (block, block_erasures) = &mut blocks[self.codeword_block(version, i)];
+            if erasures.contains(&i) {
+                block_erasures.push(block.len());
             }
-            _ => todo!(),
+            block.push(*word);
+        }
+        let ec_words = self.ec_words_per_block(version);
+        let mut data = Vec::with_capacity(self.data_code_words(version));
+        let mut corrected = 0;
+        for (block, block_erasures) in &mut blocks {
+            corrected += self.correct_block(version, block, block_erasures)?;
+            data.extend_from_slice(&block[..block.len().saturating_sub(ec_words)]);
         }
+        Ok((data, corrected))
     }
 
-    pub fn data_code_words(&self, version: u8) -> usize {
-        match self {
-            ErrorLevel::L => DATA_CAPACITY_L[version as usize].total_data_words(),
-            _ => todo!(),
+    //block of the codeword at index in the interleaved sequence add_error_codes produces
+    pub fn codeword_block(&self, version: u8, codeword: usize) -> usize {
+        let capacity_info = self.capacity(version);
+        let num_blocks = capacity_info.total_blocks().max(1);
+        let data_size = capacity_info.total_data_words();
+        //group 1 length words are taken from every block, then the extra group 2 words
+        let shared = capacity_info.words_per_grp_1 as usize * num_blocks;
+        if codeword >= data_size {
+            (codeword - data_size) % num_blocks
+        } else if codeword < shared {
+            codeword % num_blocks
+        } else {
+            capacity_info.grp_1_blks as usize + codeword - shared
         }
     }
 }
 
+//an RS block is at most 255 words of data and ecc, all zero or empty data has all zero ecc words
+fn block_ecc(
+    divisor: &gf256::Poly,
+    block_data: &[u8],
+    ecc_buffer: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    let ecc_size = divisor.degree as usize;
+    if ecc_buffer.len() < ecc_size {
+        return Err(BufferTooSmall {
+            needed_bytes: ecc_size,
+            available_bytes: ecc_buffer.len(),
+        });
+    }
+    let max_data = 255 - ecc_size;
+    if block_data.len() > max_data {
+        return Err(DataTooLong {
+            needed_bits: block_data.len() * 8,
+            capacity_bits: max_data * 8,
+        });
+    }
+    ecc_buffer[0..ecc_size].fill(0);
+    //leading zero words leave the remainder unchanged, the division needs a non zero leading term
+    let start = block_data.iter().position(|w| *w != 0);
+    let Some(block_data) = start.map(|i| &block_data[i..]) else {
+        return Ok(ecc_size);
+    };
+    let data_poly = gf256::Poly::from((block_data.len() - 1) as u8, block_data);
+    let data_p = data_poly.multiply(Term(divisor.degree, 1));
+    let remainder = data_p.div_remainder(divisor);
+    //remainder may have leading zero terms, always emit degree of divisor words
+    for term in remainder.terms() {
+        ecc_buffer[ecc_size - 1 - term.degree() as usize] = term.coef();
+    }
+    Ok(ecc_size)
+}
+
 #[cfg(test)]
 mod ecc_tests {
     use crate::error_cc::ErrorLevel;
     use crate::gf256::gf_tests::hex_str_to_bytes;
+    use crate::EncodingErr;
+
+    #[test]
+    fn test_ecc_invalid_input() {
+        let mut msg = [0u8; 3706];
+        for version in [0, 41] {
+            assert!(matches!(
+                ErrorLevel::L.add_error_codes(version, &mut msg),
+                Err(EncodingErr::UnsupportedVersion(v)) if v == version
+            ));
+        }
+        //version 1 at M is 16 data and 10 ecc words
+        assert!(matches!(
+            ErrorLevel::M.add_error_codes(1, &mut msg[..25]),
+            Err(EncodingErr::BufferTooSmall {
+                needed_bytes: 26,
+                available_bytes: 25
+            })
+        ));
+
+        let mut ecc = [0xFFu8; 30];
+        assert!(matches!(
+            ErrorLevel::L.compute_ecc(0, &[1, 2], &mut ecc),
+            Err(EncodingErr::UnsupportedVersion(0))
+        ));
+        assert!(matches!(
+            ErrorLevel::M.compute_ecc(1, &[1, 2], &mut ecc[..9]),
+            Err(EncodingErr::BufferTooSmall {
+                needed_bytes: 10,
+                available_bytes: 9
+            })
+        ));
+        assert!(matches!(
+            ErrorLevel::M.compute_ecc(1, &[0; 246], &mut ecc),
+            Err(EncodingErr::DataTooLong {
+                needed_bits: 1968,
+                capacity_bits: 1960
+            })
+        ));
+        //no data, no remainder
+        for data in [&[0u8; 245][..], &[]] {
+            ecc.fill(0xFF);
+            assert_eq!(ErrorLevel::M.compute_ecc(1, data, &mut ecc).unwrap(), 10);
+            assert!(ecc[..10].iter().all(|w| *w == 0));
+        }
+        //leading zero words do not change the ecc words
+        let data = hex_str_to_bytes("10 20 0C 56 61 80 EC 11 EC 11 EC 11 EC 11 EC 11");
+        let mut expected = [0u8; 10];
+        ErrorLevel::M.compute_ecc(1, &data, &mut expected).unwrap();
+        let padded: Vec<u8> = [0, 0].iter().chain(&data).copied().collect();
+        ErrorLevel::M.compute_ecc(1, &padded, &mut ecc).unwrap();
+        assert_eq!(&ecc[..10], &expected);
+
+        assert!(matches!(
+            ErrorLevel::L.format_bits(8),
+            Err(EncodingErr::InvalidMask)
+        ));
+    }
 
     #[test]
     fn test_error_correction() {
         let data = hex_str_to_bytes("40 D4 A4 55 35 55 32 06 96 E2 04 B4 94 E4 70 EC 11 EC 11");
         let mut ecc_words = [0; 16];
         let version = 1;
-        let ecc_size = ErrorLevel::L.compute_ecc(version, &data, &mut ecc_words);
+        let ecc_size = ErrorLevel::L
+            .compute_ecc(version, &data, &mut ecc_words)
+            .unwrap();
         assert_eq!(ecc_size, 7);
         let expected_ecc = hex_str_to_bytes("31 CA A6 14 0E 5E EC");
         assert_eq!(&expected_ecc, &ecc_words[0..ecc_size]);
     }
+
+    #[test]
+    fn test_error_correction_level_m() {
+        //version 1-M "HELLO WORLD" alphanumeric
+        let data = hex_str_to_bytes("20 5B 0B 78 D1 72 DC 4D 43 40 EC 11 EC 11 EC 11");
+        let mut ecc_words = [0; 16];
+        let ecc_size = ErrorLevel::M.compute_ecc(1, &data, &mut ecc_words).unwrap();
+        assert_eq!(ecc_size, 10);
+        let expected_ecc = hex_str_to_bytes("C4 23 27 77 EB D7 E7 E2 5D 17");
+        assert_eq!(&expected_ecc, &ecc_words[0..ecc_size]);
+    }
+
+    #[test]
+    fn test_add_error_codes_interleaves_blocks() {
+        //version 5-Q has 2 blocks of 15 words and 2 blocks of 16 words, 18 ecc words per block
+        let data_blocks: [&[u8]; 4] = [
+            &[67, 85, 70, 134, 87, 38, 85, 194, 119, 50, 6, 18, 6, 103, 38],
+            &[
+                246, 246, 66, 7, 118, 134, 242, 7, 38, 86, 22, 198, 199, 146, 6,
+            ],
+            &[
+                182, 230, 247, 119, 50, 7, 118, 134, 87, 38, 82, 6, 134, 151, 50, 7,
+            ],
+            &[
+                70, 247, 118, 86, 194, 6, 151, 50, 16, 236, 17, 236, 17, 236, 17, 236,
+            ],
+        ];
+        let ecc_blocks: [[u8; 18]; 4] = [
+            [
+                213, 199, 11, 45, 115, 247, 241, 223, 229, 248, 154, 117, 154, 111, 86, 161, 111,
+                39,
+            ],
+            [
+                87, 204, 96, 60, 202, 182, 124, 157, 200, 134, 27, 129, 209, 17, 163, 163, 120, 133,
+            ],
+            [
+                148, 116, 177, 212, 76, 133, 75, 242, 238, 76, 195, 230, 189, 10, 108, 240, 192,
+                141,
+            ],
+            [
+                235, 159, 5, 173, 24, 147, 59, 33, 106, 40, 255, 172, 82, 2, 131, 32, 178, 236,
+            ],
+        ];
+        let mut msg = [0u8; 160];
+        let data: Vec<u8> = data_blocks.concat();
+        msg[0..data.len()].copy_from_slice(&data);
+        let size = ErrorLevel::Q.add_error_codes(5, &mut msg).unwrap();
+        assert_eq!(size, 134);
+        assert_eq!(size, ErrorLevel::Q.total_words(5));
+
+        let mut expected = Vec::new();
+        for i in 0..16 {
+            for blk in data_blocks {
+                if i < blk.len() {
+                    expected.push(blk[i]);
+                }
+            }
+        }
+        for i in 0..18 {
+            for blk in ecc_blocks {
+                expected.push(blk[i]);
+            }
+        }
+        assert_eq!(&msg[0..6], &[67, 246, 182, 70, 85, 246]);
+        assert_eq!(&expected, &msg[0..size]);
+    }
+
+    #[test]
+    fn test_capacity_tables() {
+        let levels = [ErrorLevel::L, ErrorLevel::M, ErrorLevel::Q, ErrorLevel::H];
+        let expected_data_words = [(19, 16, 13, 9), (108, 86, 62, 46), (2956, 2334, 1666, 1276)];
+        for (version, expected) in [1u8, 5, 40].iter().zip(expected_data_words) {
+            let words: Vec<usize> = levels.iter().map(|l| l.data_code_words(*version)).collect();
+            assert_eq!(&words, &[expected.0, expected.1, expected.2, expected.3]);
+            //every level uses all the codewords of the version
+            for level in &levels {
+                assert_eq!(
+                    level.total_words(*version),
+                    ErrorLevel::L.total_words(*version)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_correct_code_words() {
+        let data = "lib-µQRs is tiny efficient Rust library to encode to QR code";
+        let code = crate::encode(data, ErrorLevel::Q).unwrap();
+        let version = code.version.0;
+        let (clean, corrected) = ErrorLevel::Q
+            .correct_code_words(version, code.code_words(), &[])
+            .unwrap();
+        assert_eq!(corrected, 0);
+        assert_eq!(clean.len(), ErrorLevel::Q.data_code_words(version));
+
+        //interleaving spreads a burst of errors across the blocks
+        let mut damaged = code.code_words().to_vec();
+        damaged[10..30].iter_mut().for_each(|w| *w = !*w);
+        let (data, corrected) = ErrorLevel::Q
+            .correct_code_words(version, &damaged, &[])
+            .unwrap();
+        assert_eq!((data, corrected), (clean.clone(), 20));
+
+        //erasures double the budget, 26 ecc words of a 4-Q block fix 13 errors or 26 erasures
+        let mut damaged = code.code_words().to_vec();
+        damaged[0..50].iter_mut().for_each(|w| *w = !*w);
+        assert!(ErrorLevel::Q
+            .correct_code_words(version, &damaged, &[])
+            .is_err());
+        let erasures: Vec<usize> = (0..50).collect();
+        let (data, corrected) = ErrorLevel::Q
+            .correct_code_words(version, &damaged, &erasures)
+            .unwrap();
+        assert_eq!((data, corrected), (clean, 50));
+    }
+
+    #[test]
+    fn test_codeword_block() {
+        //5-Q, 2 blocks of 15 then 2 of 16 data words, 18 ecc words each
+        let level = ErrorLevel::Q;
+        let blocks: Vec<usize> = (0..level.total_words(5))
+            .map(|i| level.codeword_block(5, i))
+            .collect();
+        assert_eq!(&blocks[0..8], [0, 1, 2, 3, 0, 1, 2, 3]);
+        assert_eq!(&blocks[60..62], [2, 3]);
+        assert_eq!(&blocks[62..66], [0, 1, 2, 3]);
+        assert_eq!(blocks[level.total_words(5) - 1], 3);
+        for b in 0..4 {
+            let words = blocks.iter().filter(|block| **block == b).count();
+            assert_eq!(words, if b < 2 { 15 } else { 16 } + 18);
+        }
+        assert_eq!(ErrorLevel::L.codeword_block(1, 25), 0);
+    }
+
+    #[test]
+    fn test_format_bits() {
+        //ISO/IEC 18004 annex C
+        let expected = [
+            (ErrorLevel::L, 0, 0b111011111000100),
+            (ErrorLevel::L, 7, 0b110100101110110),
+            (ErrorLevel::M, 0, 0b101010000010010),
+            (ErrorLevel::M, 4, 0b100010111111001),
+            (ErrorLevel::Q, 2, 0b011111100110001),
+            (ErrorLevel::Q, 7, 0b010101111101101),
+            (ErrorLevel::H, 0, 0b001011010001001),
+            (ErrorLevel::H, 5, 0b000001001010101),
+        ];
+        for (level, mask, bits) in expected {
+            assert_eq!(
+                level.format_bits(mask).unwrap(),
+                bits,
+                "{level:?} mask {mask}"
+            );
+        }
+        //any two words are at least 7 bits apart
+        let words: Vec<u32> = ErrorLevel::ALL
+            .iter()
+            .flat_map(|level| (0..8).map(|mask| level.format_bits(mask).unwrap()))
+            .collect();
+        for (i, a) in words.iter().enumerate() {
+            for b in &words[i + 1..] {
+                assert!((a ^ b).count_ones() >= 7);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest_format() {
+        let bits = ErrorLevel::Q.format_bits(6).unwrap();
+        assert_eq!(
+            ErrorLevel::nearest_format(&[bits]),
+            Some((ErrorLevel::Q, 6))
+        );
+        assert_eq!(
+            ErrorLevel::nearest_format(&[bits ^ 0b100_0001_0000_0010]),
+            Some((ErrorLevel::Q, 6))
+        );
+        //4 bits off is too far for one copy, the second copy decides
+        let damaged = bits ^ 0b1111;
+        assert_eq!(ErrorLevel::nearest_format(&[damaged]), None);
+        assert_eq!(
+            ErrorLevel::nearest_format(&[damaged, bits ^ 0b1]),
+            Some((ErrorLevel::Q, 6))
+        );
+        assert_eq!(ErrorLevel::nearest_format(&[]), None);
+    }
+
+    #[test]
+    fn test_boosted_level() {
+        //version 1 holds 152, 128, 104 and 72 data bits
+        assert_eq!(ErrorLevel::L.boosted(1, 72), ErrorLevel::H);
+        assert_eq!(ErrorLevel::L.boosted(1, 73), ErrorLevel::Q);
+        assert_eq!(ErrorLevel::L.boosted(1, 128), ErrorLevel::M);
+        assert_eq!(ErrorLevel::L.boosted(1, 129), ErrorLevel::L);
+        assert_eq!(ErrorLevel::H.boosted(1, 1), ErrorLevel::H);
+        assert_eq!(ErrorLevel::M.boosted(5, 8 * 87), ErrorLevel::M);
+    }
 }
@@ -1,3 +1,6 @@
+use crate::EncodingErr;
+use crate::EncodingErr::TooManyErrors;
+
 const MAX_DEGREE: usize = 256;
 const GF256_INVERSE: [u8; 256] = [
     0, 1, 142, 244, 71, 167, 122, 186, 173, 157, 221, 152, 61, 170, 93, 150, 216, 114, 192, 88,
@@ -34,6 +37,7 @@ pub fn get_inverse(x: u8) -> u8 {
 }
 
 #[cfg(debug_assertions)] //used for checking inverses
+#[allow(dead_code)]
 fn compute_inv_table() -> [u8; 256] {
     let mut inv = [0u8; 256];
     inv[0] = 0;
@@ -52,6 +56,7 @@ fn compute_inv_table() -> [u8; 256] {
 }
 
 #[cfg(debug_assertions)]
+#[allow(dead_code)]
 fn check_inv_table(table: &[u8]) {
     debug_assert!(table.len() == 256);
     for i in 1..=255 {
@@ -106,10 +111,7 @@ impl Poly {
                 cof[i] = coefficients[idx];
             }
         }
-        Poly {
-            degree: degree as u8,
-            cof,
-        }
+        Poly { degree, cof }
     }
 
     pub fn leading_term(&self) -> Term {
@@ -151,7 +153,7 @@ impl Poly {
         let size = self.degree as usize;
         for i in 0..=size {
             let result = gf256_mult(scalar, self.cof[i]);
-            self.cof[i as usize] = result;
+            self.cof[i] = result;
         }
     }
 
@@ -178,7 +180,7 @@ impl Poly {
         result
     }
     fn is_zero(&self) -> bool {
-        return self.cof.iter().all(|c| *c == 0);
+        self.cof.iter().all(|c| *c == 0)
     }
 
     pub fn mut_add(&mut self, term: Term) {
@@ -196,13 +198,13 @@ impl Poly {
             self.degree = term.degree();
         } else {
             let term_degree = term.degree() as usize;
-            self.cof[term_degree as usize] = gf256_add(self.cof[term_degree], term.coef());
+            self.cof[term_degree] = gf256_add(self.cof[term_degree], term.coef());
             //xor
         }
         let degree = self.degree;
-        for i in (0..=degree).rev() {
+        for i in (1..=degree).rev() {
             if self.cof[i as usize] == 0 {
-                self.degree -= 1;
+                self.degree -= 1; //zero poly stays at degree 0
             } else {
                 break;
             }
@@ -215,8 +217,8 @@ impl Poly {
         }
     }
 
-    pub fn terms(&self) -> TermIter {
-        TermIter::new(&self)
+    pub fn terms(&self) -> TermIter<'_> {
+        TermIter::new(self)
     }
 }
 
@@ -226,7 +228,7 @@ pub struct TermIter<'a> {
 }
 
 impl<'a> TermIter<'a> {
-    pub fn new(poly: &'a Poly) -> TermIter {
+    pub fn new(poly: &'a Poly) -> TermIter<'a> {
         TermIter {
             poly,
             term_idx: poly.degree + 1,
@@ -244,7 +246,7 @@ impl<'a> Iterator for TermIter<'a> {
         let index = (self.term_idx - 1) as usize;
         let coeff = self.poly.cof[index];
         self.term_idx -= 1;
-        return Some(Term(index as u8, coeff));
+        Some(Term(index as u8, coeff))
     }
 }
 
@@ -262,6 +264,188 @@ pub fn gen_polynomial(size: u8) -> Poly {
     p
 }
 
+//codeword evaluated at α^0, α^1.. the roots of gen_polynomial, all zero when there are no errors
+//codeword has the highest degree coefficient first
+pub fn syndromes(codeword: &[u8], syndromes: &mut [u8]) {
+    let mut alpha_i = 1;
+    for s in syndromes.iter_mut() {
+        *s = codeword
+            .iter()
+            .fold(0, |acc, c| gf256_mult(acc, alpha_i) ^ c);
+        alpha_i = gf256_mult(alpha_i, 2);
+    }
+}
+
+//α^i for i in 0..255, powers repeat after that
+const GF256_EXP: [u8; 255] = exp_table();
+
+const fn exp_table() -> [u8; 255] {
+    let mut table = [0u8; 255];
+    let mut x = 1u16;
+    let mut i = 0;
+    while i < 255 {
+        table[i] = x as u8;
+        x <<= 1;
+        if x > 255 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    table
+}
+
+//α^exp, negative exponents are inverses
+fn alpha_pow(exp: isize) -> u8 {
+    GF256_EXP[exp.rem_euclid(255) as usize]
+}
+
+//evaluate polynomial with the lowest degree coefficient first
+fn eval_poly(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, c| gf256_mult(acc, x) ^ c)
+}
+
+// correct codeword in place, returns number of corrected words
+//codeword has the highest degree coefficient first and ends with nsym ecc words of gen_polynomial(nsym)
+//up to nsym / 2 errors are corrected, codeword is left unchanged when there are more
+pub fn correct_errors(codeword: &mut [u8], nsym: usize) -> Result<usize, EncodingErr> {
+    correct_errors_and_erasures(codeword, nsym, &[])
+}
+
+// as correct_errors with erasures, indexes of words in codeword known to be unreliable
+//errors and erasures are corrected while 2 * errors + erasures <= nsym
+pub fn correct_errors_and_erasures(
+    codeword: &mut [u8],
+    nsym: usize,
+    erasures: &[usize],
+) -> Result<usize, EncodingErr> {
+    //a codeword is at most 255 words and needs a data word besides the ecc words
+    if codeword.len() >= MAX_DEGREE || nsym >= codeword.len() {
+        return Err(TooManyErrors);
+    }
+    if erasures.iter().any(|e| *e >= codeword.len()) {
+        return Err(TooManyErrors);
+    }
+    //an index listed twice is one erasure, a repeated root would break the locator
+    let mut erased = [false; MAX_DEGREE];
+    erasures.iter().for_each(|e| erased[*e] = true);
+    let mut erasure_buf = [0usize; MAX_DEGREE];
+    let mut count = 0;
+    for e in (0..codeword.len()).filter(|e| erased[*e]) {
+        erasure_buf[count] = e;
+        count += 1;
+    }
+    let erasures = &erasure_buf[0..count];
+    if erasures.len() > nsym {
+        return Err(TooManyErrors);
+    }
+    let mut syndrome_buf = [0u8; MAX_DEGREE];
+    let s = &mut syndrome_buf[0..nsym];
+    syndromes(codeword, s);
+    if s.iter().all(|s| *s == 0) {
+        return Ok(0);
+    }
+    let n = codeword.len();
+    //erasure locator Γ(x) = Π(1 + X x), X = α^p for an erasure at degree p
+    let mut erasure_locator = [0u8; MAX_DEGREE];
+    erasure_locator[0] = 1;
+    for (f, e) in erasures.iter().enumerate() {
+        let x = alpha_pow((n - 1 - e) as isize);
+        for i in (1..=f + 1).rev() {
+            erasure_locator[i] ^= gf256_mult(erasure_locator[i - 1], x);
+        }
+    }
+    let (locator, errata) = errata_locator(s, &erasure_locator, erasures.len());
+    let errors = errata.saturating_sub(erasures.len());
+    if errata < erasures.len() || 2 * errors + erasures.len() > nsym {
+        return Err(TooManyErrors);
+    }
+    let locator = &locator[0..=errata];
+
+    //chien search, error at degree p when Λ(α^-p) is zero
+    let mut positions = [0usize; MAX_DEGREE];
+    let mut count = 0;
+    for (j, position) in (0..n).map(|j| (j, (n - 1 - j) as isize)) {
+        if eval_poly(locator, alpha_pow(-position)) == 0 {
+            positions[count] = j;
+            count += 1;
+        }
+    }
+    if count != errata {
+        return Err(TooManyErrors);
+    }
+
+    //forney, evaluator Ω(x) = S(x)Λ(x) mod x^nsym, magnitude X Ω(X^-1) / Λ'(X^-1)
+    let mut omega = [0u8; MAX_DEGREE];
+    for (i, w) in omega[0..nsym].iter_mut().enumerate() {
+        for (j, l) in locator.iter().enumerate().take(i + 1) {
+            *w ^= gf256_mult(s[i - j], *l);
+        }
+    }
+    let mut magnitudes = [0u8; MAX_DEGREE];
+    for (k, j) in positions[0..count].iter().enumerate() {
+        let position = (n - 1 - j) as isize;
+        let x_inv = alpha_pow(-position);
+        //formal derivative keeps odd degree terms only
+        let derivative = (1..locator.len()).step_by(2).fold(0, |acc, i| {
+            acc ^ gf256_mult(locator[i], alpha_pow(-position * (i as isize - 1)))
+        });
+        if derivative == 0 {
+            return Err(TooManyErrors);
+        }
+        let value = gf256_mult(alpha_pow(position), eval_poly(&omega[0..nsym], x_inv));
+        magnitudes[k] = gf256_mult(value, get_inverse(derivative));
+    }
+    let apply = |codeword: &mut [u8]| {
+        for (k, j) in positions[0..count].iter().enumerate() {
+            codeword[*j] ^= magnitudes[k];
+        }
+    };
+    apply(codeword);
+    //more errors than nsym / 2 can look like a valid smaller set of errors
+    syndromes(codeword, &mut syndrome_buf[0..nsym]);
+    if syndrome_buf[0..nsym].iter().any(|s| *s != 0) {
+        apply(codeword);
+        return Err(TooManyErrors);
+    }
+    //erased words may have been read correctly
+    Ok(magnitudes[0..count].iter().filter(|m| **m != 0).count())
+}
+
+//berlekamp-massey started from the erasure locator
+//errata locator Λ(x) lowest degree first and its degree, the number of errors and erasures
+fn errata_locator(
+    syndromes: &[u8],
+    erasure_locator: &[u8; MAX_DEGREE],
+    erasures: usize,
+) -> ([u8; MAX_DEGREE], usize) {
+    let mut locator = *erasure_locator;
+    let mut prev = *erasure_locator;
+    let (mut errata, mut shift, mut prev_discrepancy) = (erasures, 1, 1);
+    for n in erasures..syndromes.len() {
+        let discrepancy = (1..=errata.min(n)).fold(syndromes[n], |acc, i| {
+            acc ^ gf256_mult(locator[i], syndromes[n - i])
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = gf256_mult(discrepancy, get_inverse(prev_discrepancy));
+        let current = locator;
+        for i in 0..(MAX_DEGREE - shift) {
+            locator[i + shift] ^= gf256_mult(scale, prev[i]);
+        }
+        if 2 * errata <= n + erasures {
+            errata = n + erasures + 1 - errata;
+            prev = current;
+            prev_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    (locator, errata)
+}
+
 pub fn gf256_add(x: u8, y: u8) -> u8 {
     x ^ y
 }
@@ -275,13 +459,13 @@ pub(crate) fn gf256_mult(x: u8, y: u8) -> u8 {
     while x > 0 {
         if (x & 1) == 1 {
             //odd
-            result = result ^ y; //gf add xor
+            result ^= y; //gf add xor
         }
-        x = x >> 1;
-        y = y << 1;
+        x >>= 1;
+        y <<= 1;
         if y > 255 {
             //cannot go outside gf256
-            y = y ^ 0x11d;
+            y ^= 0x11d;
         }
     }
     debug_assert!(result < 256);
@@ -292,18 +476,21 @@ pub(crate) fn gf256_mult(x: u8, y: u8) -> u8 {
 pub mod gf_tests {
 
     pub fn hex_str_to_bytes(str: &str) -> Vec<u8> {
-        let bytes: Vec<u8> = str.split_ascii_whitespace().map(|s| hex_byte(s)).collect();
+        let bytes: Vec<u8> = str.split_ascii_whitespace().map(hex_byte).collect();
         bytes
     }
 
+    use crate::gf256::correct_errors;
+    use crate::gf256::correct_errors_and_erasures;
     use crate::gf256::gen_polynomial;
     use crate::gf256::get_inverse;
     use crate::gf256::gf256_mult;
+    use crate::gf256::syndromes;
     use crate::gf256::Poly;
     use crate::gf256::Term;
 
     fn hex_byte(str: &str) -> u8 {
-        debug_assert!(str.len() < 3 && str.len() > 0);
+        debug_assert!(str.len() < 3 && !str.is_empty());
         let mut byte = 0u8;
         for (i, ch) in str.bytes().enumerate() {
             let shift = 4 * (1 - i);
@@ -407,6 +594,20 @@ pub mod gf_tests {
             &[Term(6, 6), Term(4, 34), Term(3, 64)]
         );
     }
+    #[test]
+    fn test_poly_add_cancels_constant() {
+        //cancelling the only term used to step the degree below 0
+        let mut p = Poly::from(0, &[0x5A]);
+        p.mut_add(Term(0, 0x5A));
+        assert_eq!(p.degree, 0);
+        assert!(p.is_zero());
+        let mut p = Poly::from(1, &[0x01, 0x5A]);
+        p.mut_add(Term(1, 0x01));
+        p.mut_add(Term(0, 0x5A));
+        assert_eq!(p.degree, 0);
+        assert!(p.is_zero());
+    }
+
     #[test]
     fn test_poly_mul_term() {
         let poly = Poly::from(6, &[12, 0, 34, 64, 0, 0, 0]);
@@ -451,6 +652,128 @@ pub mod gf_tests {
         );
     }
 
+    #[test]
+    fn test_syndromes() {
+        //hello world 1-M block with its 10 ecc words
+        let mut block = hex_str_to_bytes(
+            "20 5B 0B 78 D1 72 DC 4D 43 40 EC 11 EC 11 EC 11 C4 23 27 77 EB D7 E7 E2 5D 17",
+        );
+        let mut s = [0u8; 10];
+        syndromes(&block, &mut s);
+        assert_eq!(s, [0; 10]);
+        block[3] ^= 0x55;
+        syndromes(&block, &mut s);
+        assert!(s.iter().all(|s| *s != 0));
+    }
+
+    #[test]
+    fn test_correct_errors() {
+        let block = hex_str_to_bytes(
+            "20 5B 0B 78 D1 72 DC 4D 43 40 EC 11 EC 11 EC 11 C4 23 27 77 EB D7 E7 E2 5D 17",
+        );
+        let mut damaged = block.clone();
+        assert_eq!(correct_errors(&mut damaged, 10).unwrap(), 0);
+        //up to 5 errors anywhere, data or ecc words
+        for errors in 1..=5 {
+            let mut damaged = block.clone();
+            for k in 0..errors {
+                damaged[k * 5 + 1] ^= 0x5A + k as u8;
+            }
+            assert_eq!(correct_errors(&mut damaged, 10).unwrap(), errors);
+            assert_eq!(damaged, block);
+        }
+        let mut damaged = block.clone();
+        damaged[0] = 0;
+        damaged[25] ^= 0xFF;
+        assert_eq!(correct_errors(&mut damaged, 10).unwrap(), 2);
+        assert_eq!(damaged, block);
+
+        let mut damaged = block.clone();
+        for k in 0..6 {
+            damaged[k * 4] ^= 0x33;
+        }
+        let before = damaged.clone();
+        assert!(matches!(
+            correct_errors(&mut damaged, 10),
+            Err(crate::EncodingErr::TooManyErrors)
+        ));
+        assert_eq!(damaged, before);
+    }
+
+    #[test]
+    fn test_correct_erasures() {
+        let block = hex_str_to_bytes(
+            "20 5B 0B 78 D1 72 DC 4D 43 40 EC 11 EC 11 EC 11 C4 23 27 77 EB D7 E7 E2 5D 17",
+        );
+        //10 ecc words fix 10 erasures, twice the errors
+        let erasures: Vec<usize> = (3..13).collect();
+        let mut damaged = block.clone();
+        erasures.iter().for_each(|e| damaged[*e] = 0);
+        assert_eq!(
+            correct_errors_and_erasures(&mut damaged, 10, &erasures).unwrap(),
+            10
+        );
+        assert_eq!(damaged, block);
+        let mut damaged = block.clone();
+        erasures.iter().for_each(|e| damaged[*e] = 0);
+        assert!(correct_errors(&mut damaged, 10).is_err());
+
+        //2 * 3 errors + 4 erasures, one erased word read correctly
+        let mut damaged = block.clone();
+        for e in [0, 9, 20] {
+            damaged[e] ^= 0xA7;
+        }
+        for e in [2, 5, 14] {
+            damaged[e] = !damaged[e];
+        }
+        assert_eq!(
+            correct_errors_and_erasures(&mut damaged, 10, &[2, 5, 14, 25]).unwrap(),
+            6
+        );
+        assert_eq!(damaged, block);
+
+        //2 * 4 errors + 4 erasures is over budget
+        let mut damaged = block.clone();
+        for e in [0, 9, 20, 22, 2, 5, 14, 25] {
+            damaged[e] ^= 0x3C;
+        }
+        let before = damaged.clone();
+        assert!(correct_errors_and_erasures(&mut damaged, 10, &[2, 5, 14, 25]).is_err());
+        assert_eq!(damaged, before);
+        assert!(correct_errors_and_erasures(&mut damaged, 10, &[26]).is_err());
+
+        //repeated indexes count once, 10 listed erasures over 6 words
+        let mut damaged = block.clone();
+        for e in [1, 4, 8, 17, 19, 24] {
+            damaged[e] = 0;
+        }
+        let erasures = [4, 1, 4, 8, 17, 17, 19, 24, 1, 8];
+        assert_eq!(
+            correct_errors_and_erasures(&mut damaged, 10, &erasures).unwrap(),
+            6
+        );
+        assert_eq!(damaged, block);
+    }
+
+    #[test]
+    fn test_correct_invalid_codeword() {
+        let mut long = vec![0u8; 256];
+        assert!(matches!(
+            correct_errors(&mut long, 10),
+            Err(crate::EncodingErr::TooManyErrors)
+        ));
+        let mut block = hex_str_to_bytes("20 5B 0B 78");
+        assert!(matches!(
+            correct_errors(&mut block, 4),
+            Err(crate::EncodingErr::TooManyErrors)
+        ));
+        assert!(matches!(
+            correct_errors(&mut block, 300),
+            Err(crate::EncodingErr::TooManyErrors)
+        ));
+        assert_eq!(block, hex_str_to_bytes("20 5B 0B 78"));
+    }
+
     #[test]
     fn test_poly_remainder() {
         // let poly_1 = Poly::from(&[0x12, 0x34, 0x56, 0x00, 0x00, 0x00, 0x00]);
@@ -514,7 +837,7 @@ pub mod gf_tests {
         let terms: Vec<u8> = remainder.terms().map(|t| t.coef()).collect();
         let expected: Vec<u8> = "5C 5A 9A 55 CB 35 7F"
             .split_ascii_whitespace()
-            .map(|s| hex_byte(s))
+            .map(hex_byte)
             .collect();
         assert_eq!(&terms, &expected);
     }
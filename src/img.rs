@@ -1,29 +1,178 @@
-use crate::Version;
+use crate::{EncodingErr, Version};
 use std::fs::File;
 use std::io::Write;
 
 pub mod ppm {
-    use crate::img::{Canvas, RED, RGB, WHITE};
-    use crate::Code;
+    use crate::img::{Canvas, RGB};
+    use crate::{Code, EncodingErr};
     use std::io::Write;
 
-    pub fn to_img<const S: usize>(code: &Code<S>, colors: [RGB; 2], writer: &mut impl Write) {
+    pub fn to_img(
+        code: &Code,
+        colors: [RGB; 2],
+        writer: &mut impl Write,
+    ) -> Result<(), EncodingErr> {
         let mut img = Canvas::for_version(code.version);
-        for m in code.version.reserved_iter() {
-            let (x, y) = m.position();
-            let i = usize::from(m.is_dark());
-            img.set_pixel(x as u32, y as u32, &colors[i]);
+        for (y, row) in code.matrix()?.rows().enumerate() {
+            for (x, is_dark) in row.enumerate() {
+                img.set_pixel(x as u32, y as u32, &colors[usize::from(is_dark)]);
+            }
+        }
+        img.write_header(writer)?;
+        img.write(writer)
+    }
+}
+
+//Netpbm P1 to P6, ASCII and binary, read as 8 bit greyscale
+pub mod pnm {
+    use crate::EncodingErr;
+    use crate::EncodingErr::InvalidImage;
+    use std::io::Read;
+
+    //pixels in row major order, 0 is black and 255 white
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct GreyImage {
+        pub width: u32,
+        pub height: u32,
+        pub pixels: Vec<u8>,
+    }
+
+    impl GreyImage {
+        //pixel at column x, row y, None outside the image
+        pub fn get(&self, x: u32, y: u32) -> Option<u8> {
+            (x < self.width && y < self.height)
+                .then(|| self.pixels[y as usize * self.width as usize + x as usize])
+        }
+    }
+
+    //header fields may be separated by comments, samples above 255 are 2 bytes big endian in binary
+    //colour is converted with Rec. 601 luma weights
+    pub fn read(reader: &mut impl Read) -> Result<GreyImage, EncodingErr> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let kind = match bytes.get(..2) {
+            Some([b'P', kind @ b'1'..=b'6']) => kind - b'0',
+            _ => return Err(InvalidImage),
+        };
+        let mut p = Parser {
+            bytes: &bytes,
+            pos: 2,
+        };
+        let width = p.number()?;
+        let height = p.number()?;
+        let maxval = if matches!(kind, 1 | 4) {
+            1
+        } else {
+            p.number()?
+        };
+        if width == 0 || height == 0 || maxval == 0 || maxval > u16::MAX as u32 {
+            return Err(InvalidImage);
+        }
+        let channels = if matches!(kind, 3 | 6) { 3 } else { 1 };
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or(InvalidImage)?;
+        //a set bit is black in PBM, inverted it reads as a sample with maxval 1
+        let samples: Vec<u32> = match kind {
+            1 => (0..count).map(|_| p.bit().map(|b| 1 - b)).collect(),
+            2 | 3 => (0..count).map(|_| p.number()).collect(),
+            4 => {
+                let row_bytes = (width as usize).div_ceil(8);
+                let len = row_bytes.checked_mul(height as usize).ok_or(InvalidImage)?;
+                let raster = p.raster(len)?;
+                Ok(raster
+                    .chunks(row_bytes)
+                    .flat_map(|row| {
+                        (0..width as usize).map(|x| 1 - u32::from((row[x / 8] >> (7 - x % 8)) & 1))
+                    })
+                    .collect())
+            }
+            _ if maxval > 255 => Ok(p
+                .raster(count.checked_mul(2).ok_or(InvalidImage)?)?
+                .chunks(2)
+                .map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])))
+                .collect()),
+            _ => Ok(p.raster(count)?.iter().map(|b| u32::from(*b)).collect()),
+        }?;
+        if samples.iter().any(|s| *s > maxval) {
+            return Err(InvalidImage);
+        }
+        let scale = |s: u32| (s * 255 + maxval / 2) / maxval;
+        let pixels = samples
+            .chunks(channels)
+            .map(|c| match c {
+                [r, g, b] => {
+                    ((299 * scale(*r) + 587 * scale(*g) + 114 * scale(*b) + 500) / 1000) as u8
+                }
+                _ => scale(c[0]) as u8,
+            })
+            .collect();
+        Ok(GreyImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    struct Parser<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl Parser<'_> {
+        //whitespace and comments, which run from # to the end of the line
+        fn skip_space(&mut self) {
+            while let Some(b) = self.bytes.get(self.pos) {
+                match b {
+                    b'#' => {
+                        while !matches!(self.bytes.get(self.pos), None | Some(b'\n' | b'\r')) {
+                            self.pos += 1;
+                        }
+                    }
+                    b if b.is_ascii_whitespace() => self.pos += 1,
+                    _ => break,
+                }
+            }
+        }
+
+        fn number(&mut self) -> Result<u32, EncodingErr> {
+            self.skip_space();
+            let start = self.pos;
+            while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                self.pos += 1;
+            }
+            std::str::from_utf8(&self.bytes[start..self.pos])
+                .ok()
+                .and_then(|digits| digits.parse().ok())
+                .ok_or(InvalidImage)
         }
-        for module in code.module_iter() {
-            let i = usize::from(module.is_dark());
-            let (x, y) = module.position();
-            img.set_pixel(x as u32, y as u32, &colors[i]);
+
+        //P1 pixels need no whitespace between them
+        fn bit(&mut self) -> Result<u32, EncodingErr> {
+            self.skip_space();
+            let bit = match self.bytes.get(self.pos) {
+                Some(b'0') => 0,
+                Some(b'1') => 1,
+                _ => return Err(InvalidImage),
+            };
+            self.pos += 1;
+            Ok(bit)
+        }
+
+        //binary samples after the single whitespace that ends the header
+        fn raster(&mut self, len: usize) -> Result<&[u8], EncodingErr> {
+            if !self
+                .bytes
+                .get(self.pos)
+                .is_some_and(u8::is_ascii_whitespace)
+            {
+                return Err(InvalidImage);
+            }
+            let start = self.pos + 1;
+            let end = start.checked_add(len).ok_or(InvalidImage)?;
+            self.bytes.get(start..end).ok_or(InvalidImage)
         }
-        //write header
-        writer
-            .write_all(format!("P6 {} {} 255 ", img.width, img.height).as_bytes())
-            .expect("error writing header");
-        img.write(writer);
     }
 }
 
@@ -57,19 +206,20 @@ impl Canvas {
         }
     }
 
-    pub fn write_to_file(&mut self, filename: &str) {
-        let mut file = init_ppm(filename, self.width, self.height);
-        self.write(&mut file);
+    pub fn write_to_file(&mut self, filename: &str) -> Result<(), EncodingErr> {
+        let mut file = File::create(format!("{}.ppm", filename))?;
+        self.write_header(&mut file)?;
+        self.write(&mut file)
     }
 
-    pub fn write_header(&self, writer: &mut impl Write) {
-        writer
-            .write_all(format!("P6 {} {} 255 ", self.width, self.height).as_bytes())
-            .expect("error writing header");
+    pub fn write_header(&self, writer: &mut impl Write) -> Result<(), EncodingErr> {
+        writer.write_all(format!("P6 {} {} 255 ", self.width, self.height).as_bytes())?;
+        Ok(())
     }
-    pub fn write(&mut self, w: &mut impl Write) {
+    pub fn write(&mut self, w: &mut impl Write) -> Result<(), EncodingErr> {
         let bytes = &serialize_rgb(&self.pixels, (self.width * self.height) as usize);
-        w.write_all(bytes).expect("error");
+        w.write_all(bytes)?;
+        Ok(())
     }
 
     pub fn set_pixel(&mut self, x: u32, y: u32, color: &RGB) {
@@ -80,7 +230,7 @@ impl Canvas {
                 self.set_colour(
                     (x + quite_zone) * pixel_size + i,
                     (y + quite_zone) * pixel_size + j,
-                    &color,
+                    color,
                 );
             }
         }
@@ -89,7 +239,7 @@ impl Canvas {
     pub fn for_version(v: Version) -> Canvas {
         let size = v.square_size() as u32;
         let canvas_size: u32 =
-            ((size + Self::DEFAULT_QUITE_ZONE_SIZE as u32 * 2) * Self::PIXEL_PER_MOD as u32) as u32;
+            (size + Self::DEFAULT_QUITE_ZONE_SIZE as u32 * 2) * Self::PIXEL_PER_MOD as u32;
 
         Canvas::new(
             canvas_size,
@@ -104,20 +254,13 @@ impl Canvas {
         Canvas {
             width,
             height,
-            quite_zone: quite_zone,
-            pixel_size: pixel_size,
+            quite_zone,
+            pixel_size,
             pixels: vec![bg_color; (width * height) as usize],
         }
     }
 }
 
-fn init_ppm(filename: &str, width: u32, height: u32) -> File {
-    let mut file = File::create(format!("{}.ppm", filename)).expect("couldn't create");
-    file.write_all(format!("P6 {} {} 255 ", width, height).as_bytes())
-        .expect("error writing to a file");
-    file
-}
-
 pub const WHITE: RGB = RGB(255, 255, 255);
 pub const RED: RGB = RGB(255, 0, 0);
 
@@ -127,3 +270,92 @@ pub const YELLOW: RGB = RGB(255, 0, 255);
 pub const ORANGE: RGB = RGB(255, 165, 0);
 
 pub const BLACK: RGB = RGB(0, 0, 0);
+
+#[cfg(test)]
+mod pnm_tests {
+    use crate::error_cc::ErrorLevel;
+    use crate::img::pnm::{read, GreyImage};
+    use crate::img::{ppm, BLACK, WHITE};
+    use crate::{encode, EncodingErr};
+
+    fn read_bytes(bytes: &[u8]) -> Result<GreyImage, EncodingErr> {
+        read(&mut &bytes[..])
+    }
+
+    #[test]
+    fn test_read_ascii() {
+        let pbm = read_bytes(b"P1\n# checker\n3 2\n1 0 1\n010").unwrap();
+        assert_eq!((pbm.width, pbm.height), (3, 2));
+        assert_eq!(pbm.pixels, [0, 255, 0, 255, 0, 255]);
+
+        let pgm = read_bytes(b"P2 2 2 # comment after width\n 4\n0 1\n2 4\n").unwrap();
+        assert_eq!(pgm.pixels, [0, 64, 128, 255]);
+
+        let ppm = read_bytes(b"P3 2 1 1000 1000 0 0 0 0 1000").unwrap();
+        assert_eq!(ppm.pixels, [76, 29]);
+        assert_eq!(ppm.get(1, 0), Some(29));
+        assert_eq!(ppm.get(2, 0), None);
+    }
+
+    #[test]
+    fn test_read_binary() {
+        //rows are padded to a whole byte
+        let pbm = read_bytes(b"P4\n10 2\n\xC0\x40\x00\x3F").unwrap();
+        let row = |pixels: &[u8]| pixels.iter().filter(|p| **p == 0).count();
+        assert_eq!(pbm.pixels.len(), 20);
+        assert_eq!(&pbm.pixels[..3], [0, 0, 255]);
+        assert_eq!(pbm.pixels[9], 0);
+        assert_eq!(row(&pbm.pixels[10..]), 0);
+
+        let pgm = read_bytes(b"P5 3 1 255\n\x00\x80\xFF").unwrap();
+        assert_eq!(pgm.pixels, [0, 128, 255]);
+        //16 bit samples
+        let pgm = read_bytes(b"P5 2 1 65535\n\x00\x00\xFF\xFF").unwrap();
+        assert_eq!(pgm.pixels, [0, 255]);
+        let ppm = read_bytes(b"P6 1 1 511 \x01\xFF\x00\x00\x00\x00").unwrap();
+        assert_eq!(ppm.pixels, [76]);
+    }
+
+    #[test]
+    fn test_read_code_image() {
+        let code = encode("isaiah-perumalla", ErrorLevel::Q).unwrap();
+        let mut bytes = Vec::new();
+        ppm::to_img(&code, [WHITE, BLACK], &mut bytes).unwrap();
+        let img = read_bytes(&bytes).unwrap();
+        let matrix = code.matrix().unwrap();
+        let size = matrix.size();
+        //8 pixels per module and a 2 module quiet zone
+        assert_eq!(img.width, (size as u32 + 4) * 8);
+        assert_eq!(img.height, img.width);
+        for y in 0..size {
+            for x in 0..size {
+                let pixel = img.get((x as u32 + 2) * 8 + 4, (y as u32 + 2) * 8 + 4);
+                assert_eq!(pixel == Some(0), matrix.get(x, y), "module ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let invalid: [&[u8]; 10] = [
+            b"P7 1 1 255\n\x00",
+            b"P2 0 1 255 0",
+            b"P2 1 1 0 0",
+            b"P2 1 1 65536 0",
+            b"P2 1 1 3 4",
+            b"P2 2 1 255 0",
+            b"P5 2 1 255\n\x00",
+            b"P1 1 1 2",
+            //sizes whose byte count overflows
+            b"P5 4294967295 4294967295 65535\n\x00",
+            b"P6 4294967295 4294967295 255\n\x00",
+        ];
+        for bytes in invalid {
+            let result = read_bytes(bytes);
+            assert!(
+                matches!(result, Err(EncodingErr::InvalidImage)),
+                "{bytes:?}"
+            );
+        }
+    }
+}
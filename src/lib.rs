@@ -1,59 +1,194 @@
+//the package name microQRs is not snake case, the lint can only be allowed for the whole crate
+#![allow(non_snake_case)]
 extern crate core;
 
+use crate::append::StructuredAppend;
 use crate::bits::{BigEndianBitWriter, MsbBitIter};
+use crate::builder::QrBuilder;
 use crate::error_cc::ErrorLevel;
-use EncodingErr::DataTooLong;
+use crate::matrix::QrMatrix;
+use EncodingErr::{
+    BufferTooSmall, DataTooLong, InvalidBitCount, InvalidData, InvalidEci, InvalidFnc1,
+    InvalidFormat, InvalidImage, InvalidMask, InvalidMatrix, InvalidStructuredAppend, Io,
+    NotAlphaNumeric, NotAscii, NotKanji, NotNumeric, ParityMismatch, TooManyErrors,
+    UnsupportedVersion,
+};
 
+pub mod append;
 pub mod bits;
+pub mod builder;
+pub mod capacity;
+pub mod decode;
 pub mod error_cc;
 pub mod gf256;
 pub mod img;
-pub fn encode<const S: usize>(data: &str) -> Result<Code<S>, EncodingErr> {
-    let mut encoded = [0; S];
-    const MAX_VERSION: u8 = 5u8;
-    let size = encode_byte_segment(data, &mut encoded)?;
-    if size > S {
-        return Err(DataTooLong);
-    }
-    let err_level = ErrorLevel::L;
-    let v = (1..=MAX_VERSION)
-        .filter(|v| err_level.data_code_words(*v) >= size)
-        .map(|v| Version(v))
-        .next();
-    if v.is_none() {
-        return Err(DataTooLong);
-    }
-    let version = v.unwrap();
-    if err_level.total_words(version.0) >= S {
-        return Err(DataTooLong);
-    }
-    let padding = err_level.data_code_words(version.0) - size;
-    add_padding(&mut encoded[size..(size + padding)]);
-    let size = err_level.add_error_codes(version.0, &mut encoded);
-    let code_words = &encoded[0..size];
-    let expected_bytes = err_level.total_words(version.0);
-
-    debug_assert!(
-        code_words.len() == expected_bytes,
-        "code_words len for version {}, {} but was {}",
-        version.0,
-        expected_bytes,
-        code_words.len()
-    );
-    Ok(Code {
-        version,
+pub mod mask;
+pub mod matrix;
+pub mod optimise;
+pub mod sjis;
+pub fn encode(data: &str, err_level: ErrorLevel) -> Result<Code, EncodingErr> {
+    let options = EncodeOptions {
         err_level,
-        data: encoded,
+        ..Default::default()
+    };
+    encode_with_headers(data, SegmentHeaders::default(), options)
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EncodeOptions {
+    pub err_level: ErrorLevel,
+    //raise err_level when the smallest version that holds data has room for a higher level
+    pub boost_err_level: bool,
+}
+
+pub fn encode_with_options(data: &str, options: EncodeOptions) -> Result<Code, EncodingErr> {
+    encode_with_headers(data, SegmentHeaders::default(), options)
+}
+
+// encode binary data in one byte segment of the smallest version that holds it
+pub fn encode_binary(data: &[u8], err_level: ErrorLevel) -> Result<Code, EncodingErr> {
+    QrBuilder::new()
+        .push(Segment::Bytes(data))
+        .error_level(err_level)
+        .build()
+}
+
+// encode data as GS1 (FNC1 first) or AIM (FNC1 second) payload
+//GS (0x1D) separates element strings, written as % in alphanumeric segments
+pub fn encode_fnc1(data: &str, fnc1: Fnc1, err_level: ErrorLevel) -> Result<Code, EncodingErr> {
+    let headers = SegmentHeaders {
+        fnc1: Some(fnc1),
+        ..Default::default()
+    };
+    let options = EncodeOptions {
+        err_level,
+        ..Default::default()
+    };
+    encode_with_headers(data, headers, options)
+}
+
+fn encode_with_headers(
+    data: &str,
+    headers: SegmentHeaders,
+    options: EncodeOptions,
+) -> Result<Code, EncodingErr> {
+    let (version, headers, segments) =
+        fit_segments(data, headers, Version::MAX, options.err_level)?;
+    let mut encoded = vec![0; version.code_words()];
+    let size = write_segments(headers, &segments, version, &mut encoded)?;
+    let err_level = if options.boost_err_level {
+        let bits = header_segments_bits(headers, &segments, version);
+        options.err_level.boosted(version.0, bits)
+    } else {
+        options.err_level
+    };
+    Code::from_data_words(encoded, size, version, err_level, None)
+}
+
+//version, headers and segments of data ready to write
+type FittedSegments<'a> = (Version, SegmentHeaders, Vec<(Mode, &'a str)>);
+
+//smallest version up to max_version that holds headers and the optimal segments of data
+//ECI header is added to headers when segments need it
+fn fit_segments(
+    data: &str,
+    headers: SegmentHeaders,
+    max_version: Version,
+    err_level: ErrorLevel,
+) -> Result<FittedSegments<'_>, EncodingErr> {
+    //count field widths change at version 10 and 27, optimise for each range
+    const VERSION_RANGES: [(u8, u8); 3] = [(1, 9), (10, 26), (27, 40)];
+    let fnc1 = headers.fnc1.is_some();
+    let mut needed_bits = 0;
+    for (first, last) in VERSION_RANGES {
+        if first > max_version.0 {
+            break;
+        }
+        let last = Version(last.min(max_version.0));
+        //an ECI header given by the caller costs the same whatever the segments
+        let eci_bits = match headers.eci {
+            Some(_) => 0,
+            None => Eci::UTF8.segment_bits(),
+        };
+        let segments = optimise::optimal_segments(data, last, true, fnc1, eci_bits);
+        let headers = SegmentHeaders {
+            eci: headers.eci.or(Eci::for_segments(&segments)),
+            ..headers
+        };
+        needed_bits = header_segments_bits(headers, &segments, last);
+        if let Some(version) = (first..=last.0)
+            .map(Version)
+            .find(|v| err_level.data_bits(v.0) >= needed_bits)
+        {
+            return Ok((version, headers, segments));
+        }
+    }
+    Err(DataTooLong {
+        needed_bits,
+        capacity_bits: err_level.data_bits(max_version.0),
     })
 }
 
-pub struct Code<const S: usize> {
+pub struct Code {
     pub version: Version,
     pub err_level: ErrorLevel,
-    pub data: [u8; S],
+    pub mask: u8,
+    pub data: Vec<u8>, //code words of version, data and ecc interleaved
 }
 
-impl<const S: usize> Code<S> {
+impl Code {
+    //pad size bytes of segment data, add error correction and choose mask
+    //terminator bits past the data capacity are dropped
+    fn from_data_words(
+        mut encoded: Vec<u8>,
+        size: usize,
+        version: Version,
+        err_level: ErrorLevel,
+        mask: Option<u8>,
+    ) -> Result<Code, EncodingErr> {
+        if !version.is_valid() {
+            return Err(UnsupportedVersion(version.0));
+        }
+        if mask.is_some_and(|m| m as usize >= MASK_FN.len()) {
+            return Err(InvalidMask);
+        }
+        encoded.resize(version.code_words(), 0);
+        let size = size.min(err_level.data_code_words(version.0));
+        let padding = err_level.data_code_words(version.0) - size;
+        add_padding(&mut encoded[size..(size + padding)]);
+        let size = err_level.add_error_codes(version.0, &mut encoded)?;
+        let code_words = &encoded[0..size];
+        let expected_bytes = err_level.total_words(version.0);
+
+        debug_assert!(
+            code_words.len() == expected_bytes,
+            "code_words len for version {}, {} but was {}",
+            version.0,
+            expected_bytes,
+            code_words.len()
+        );
+        //self check, every generated block decodes without corrections
+        debug_assert!(
+            matches!(
+                err_level.correct_code_words(version.0, code_words, &[]),
+                Ok((_, 0))
+            ),
+            "ecc words of version {} do not match the data",
+            version.0
+        );
+        let mut code = Code {
+            version,
+            err_level,
+            mask: 0,
+            data: encoded,
+        };
+        code.mask = match mask {
+            Some(mask) => mask,
+            None => code.best_mask()?,
+        };
+        Ok(code)
+    }
+
     pub fn code_words(&self) -> &[u8] {
         let num_words = self.err_level.total_words(self.version.0);
         &self.data[0..num_words]
@@ -68,30 +203,54 @@ impl<const S: usize> Code<S> {
         std::iter::zip(data_it, bit_iter)
     }
 
-    pub fn module_iter(&self) -> impl Iterator<Item = Module> + '_ {
-        let mask_level = 0;
+    //mask pattern with the lowest penalty score
+    pub fn best_mask(&self) -> Result<u8, EncodingErr> {
+        let size = self.version.square_size() as usize;
+        let mut modules = vec![false; size * size];
+        let mut best = (u32::MAX, 0);
+        for mask in 0..(MASK_FN.len() as u8) {
+            for m in self.masked_module_iter(mask)? {
+                let (x, y) = m.position();
+                modules[y as usize * size + x as usize] = m.is_dark();
+            }
+            let score = mask::penalty_score(&modules, size);
+            if score < best.0 {
+                best = (score, mask);
+            }
+        }
+        Ok(best.1)
+    }
+
+    //every module of the symbol with the chosen mask applied
+    pub fn matrix(&self) -> Result<QrMatrix, EncodingErr> {
+        Ok(QrMatrix::from_modules(self.version, self.module_iter()?))
+    }
+
+    //fails for a mask outside 0 to 7
+    pub fn module_iter(&self) -> Result<impl Iterator<Item = Module> + '_, EncodingErr> {
+        self.masked_module_iter(self.mask)
+    }
+
+    fn masked_module_iter(
+        &self,
+        mask_level: u8,
+    ) -> Result<impl Iterator<Item = Module> + '_, EncodingErr> {
         let version_num = self.version.0;
-        let format_modules = self.version.format_modules(self.err_level, mask_level);
+        let format_modules = self.version.format_modules(self.err_level, mask_level)?;
         let mut reserved_it = Version(version_num).reserved_iter();
-        let mut data_it = Version(version_num).data_region_iter();
+        let mut data_it = Version(version_num).data_module_types(self.err_level);
 
         let num_words = self.err_level.total_words(version_num);
         let code_words = &self.data[0..num_words];
         let mut bit_iter = MsbBitIter::new(code_words);
         let mut format_index = 0;
-        std::iter::from_fn(move || {
+        Ok(std::iter::from_fn(move || {
             if let Some(m) = reserved_it.next() {
                 Some(m)
-            } else if let Some((x, y)) = data_it.next() {
-                let bit = match bit_iter.next() {
-                    Some(bit) => bit,
-                    _ => false,
-                };
-                if MASK_FN[mask_level as usize]((x, y)) {
-                    Some(Module::data((x, y), !bit))
-                } else {
-                    Some(Module::data((x, y), bit))
-                }
+            } else if let Some((pos, module_type)) = data_it.next() {
+                let bit = bit_iter.next().unwrap_or_default();
+                let is_dark = bit != MASK_FN[mask_level as usize](pos);
+                Some(Module::new(pos, is_dark, module_type))
             } else if format_index < 30 {
                 let i = format_index;
                 format_index += 1;
@@ -99,62 +258,156 @@ impl<const S: usize> Code<S> {
             } else {
                 None
             }
-        })
+        }))
     }
 }
-#[derive(Copy, Clone, Debug)]
-pub struct Module((u8, u8), u8); //position and flags
+//part of the symbol a module belongs to
+//codeword is the index into Code::code_words, block the error correction block it belongs to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModuleType {
+    Finder,
+    Separator,
+    Timing,
+    Alignment,
+    FormatInfo,
+    VersionInfo,
+    DarkModule,
+    DataCodeword { codeword: u16, block: u8 },
+    EccCodeword { codeword: u16, block: u8 },
+    Remainder, //data region bits after the last codeword
+}
+
+impl ModuleType {
+    pub fn is_function(&self) -> bool {
+        !matches!(
+            self,
+            ModuleType::DataCodeword { .. }
+                | ModuleType::EccCodeword { .. }
+                | ModuleType::Remainder
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Module((u8, u8), bool, ModuleType); //position, is dark and type
 impl Module {
-    const IS_DARK_MASK: u8 = 1u8 << 7;
-    const TYPE_MASK: u8 = 0xF;
-    const TYPE_DATA: u8 = 0x1;
-    const TYPE_RESERVED: u8 = 0x0;
+    pub fn new(position: (u8, u8), is_dark: bool, module_type: ModuleType) -> Module {
+        Module(position, is_dark, module_type)
+    }
+
     pub fn is_dark(&self) -> bool {
-        let flags = self.1;
-        0 != flags & Self::IS_DARK_MASK // bit 0 is is dark
+        self.1
     }
 
     pub fn position(&self) -> (u8, u8) {
         self.0
     }
-    pub fn data(position: (u8, u8), is_dark: bool) -> Module {
-        let mut flags = Self::TYPE_DATA;
-        if is_dark {
-            flags |= Self::IS_DARK_MASK;
-        }
-        Module(position, flags)
-    }
 
-    pub fn reserved(position: (u8, u8), is_dark: bool) -> Module {
-        let mut flags = Self::TYPE_RESERVED;
-        if is_dark {
-            flags |= Self::IS_DARK_MASK;
-        }
-        Module(position, flags)
+    pub fn module_type(&self) -> ModuleType {
+        self.2
     }
 
+    //data region module, codeword or remainder bit
     pub fn is_data(&self) -> bool {
-        let flags = self.1;
-        Self::TYPE_DATA == (flags & Self::TYPE_MASK)
+        !self.2.is_function()
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Version(pub u8);
 
 impl Version {
-    //alignment square position for version 1-5
-    const ALIGNMENT_POSITIONS: [&'static [(u8, u8)]; 6] =
-        [&[], &[], &[(18, 18)], &[(22, 22)], &[(26, 26)], &[(30, 30)]];
+    pub const MAX: Version = Version(40);
+
+    pub fn new(version: u8) -> Result<Version, EncodingErr> {
+        let version = Version(version);
+        if version.is_valid() {
+            Ok(version)
+        } else {
+            Err(UnsupportedVersion(version.0))
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (1..=Self::MAX.0).contains(&self.0)
+    }
+
+    //data and ecc code words of the symbol, the same for every error level
+    //modules left once function patterns and format/version info are removed, 8 per word
+    pub const fn code_words(&self) -> usize {
+        let v = self.0 as usize;
+        if v == 0 || v > Self::MAX.0 as usize {
+            return 0;
+        }
+        let mut modules = (16 * v + 128) * v + 64;
+        if v >= 2 {
+            let align = v / 7 + 2;
+            modules -= (25 * align - 10) * align - 55;
+            if v >= 7 {
+                modules -= 36;
+            }
+        }
+        modules / 8
+    }
+    //row/column coordinates of alignment square centers, squares are placed at every
+    //combination except the three that overlap the finder patterns
+    const ALIGNMENT_COORDINATES: [&'static [u8]; 41] = [
+        &[],
+        &[],                             //v1
+        &[6, 18],                        //v2
+        &[6, 22],                        //v3
+        &[6, 26],                        //v4
+        &[6, 30],                        //v5
+        &[6, 34],                        //v6
+        &[6, 22, 38],                    //v7
+        &[6, 24, 42],                    //v8
+        &[6, 26, 46],                    //v9
+        &[6, 28, 50],                    //v10
+        &[6, 30, 54],                    //v11
+        &[6, 32, 58],                    //v12
+        &[6, 34, 62],                    //v13
+        &[6, 26, 46, 66],                //v14
+        &[6, 26, 48, 70],                //v15
+        &[6, 26, 50, 74],                //v16
+        &[6, 30, 54, 78],                //v17
+        &[6, 30, 56, 82],                //v18
+        &[6, 30, 58, 86],                //v19
+        &[6, 34, 62, 90],                //v20
+        &[6, 28, 50, 72, 94],            //v21
+        &[6, 26, 50, 74, 98],            //v22
+        &[6, 30, 54, 78, 102],           //v23
+        &[6, 28, 54, 80, 106],           //v24
+        &[6, 32, 58, 84, 110],           //v25
+        &[6, 30, 58, 86, 114],           //v26
+        &[6, 34, 62, 90, 118],           //v27
+        &[6, 26, 50, 74, 98, 122],       //v28
+        &[6, 30, 54, 78, 102, 126],      //v29
+        &[6, 26, 52, 78, 104, 130],      //v30
+        &[6, 30, 56, 82, 108, 134],      //v31
+        &[6, 34, 60, 86, 112, 138],      //v32
+        &[6, 30, 58, 86, 114, 142],      //v33
+        &[6, 34, 62, 90, 118, 146],      //v34
+        &[6, 30, 54, 78, 102, 126, 150], //v35
+        &[6, 24, 50, 76, 102, 128, 154], //v36
+        &[6, 28, 54, 80, 106, 132, 158], //v37
+        &[6, 32, 58, 84, 110, 136, 162], //v38
+        &[6, 26, 54, 82, 110, 138, 166], //v39
+        &[6, 30, 58, 86, 114, 142, 170], //v40
+    ];
+    //saturates past u8 for versions above 40 rather than overflow
     pub fn square_size(&self) -> u8 {
-        4 * self.0 + 17
+        self.0.saturating_mul(4).saturating_add(17)
     }
 
-    pub fn format_modules(&self, err_level: ErrorLevel, mask_level: u8) -> [Module; 30] {
-        let mut mask_module = [Module::reserved((0, 0), false); 30];
-        let new_mod = |pos, bit| Module::reserved(pos, bit);
+    pub fn format_modules(
+        &self,
+        err_level: ErrorLevel,
+        mask_level: u8,
+    ) -> Result<[Module; 30], EncodingErr> {
+        let mut mask_module = [Module::new((0, 0), false, ModuleType::FormatInfo); 30];
+        let new_mod = |pos, bit| Module::new(pos, bit, ModuleType::FormatInfo);
         let mut index = 0;
-        let bits = err_level.format_bits(mask_level);
+        let bits = err_level.format_bits(mask_level)?;
         debug_assert!((bits >> 15) == 0, "format must be 15 bits");
         let bit = |i| 0 != (bits & (1u32 << i)); //is ith bit set
         for i in 0..6 {
@@ -185,7 +438,7 @@ impl Version {
             mask_module[index] = new_mod((8, square_size - 15 + i), bit(i));
             index += 1;
         }
-        mask_module
+        Ok(mask_module)
     }
 
     fn timing_pattern_iter(&self) -> impl Iterator<Item = (u8, u8, bool)> {
@@ -230,7 +483,7 @@ impl Version {
         })
     }
     fn dark_module_location(&self) -> (u8, u8) {
-        (8, (4 * self.0 + 9))
+        (8, self.square_size() - 8)
     }
 
     pub fn is_data_location(&self, location: (u8, u8)) -> bool {
@@ -251,6 +504,14 @@ impl Version {
         if reserved.iter().any(|rect| rect.contains(location)) {
             return false;
         }
+        let version_info = [
+            Rect((size - 11, 0), (size - 9, 5)), //top right
+            Rect((0, size - 11), (5, size - 9)), //bottom left
+        ];
+        if self.has_version_info() && version_info.iter().any(|rect| rect.contains(location)) {
+            return false;
+        }
+
         if self
             .alignment_squares_iter()
             .any(|sq| sq.contains(location))
@@ -268,16 +529,60 @@ impl Version {
     }
 
     fn alignment_squares_iter(&self) -> impl Iterator<Item = ConcentricSquare> {
-        let mut i = 0;
-        let square_positions = Self::ALIGNMENT_POSITIONS[self.0 as usize];
-        std::iter::from_fn(move || {
-            if i < square_positions.len() {
-                let pos = square_positions[i];
-                i += 1;
-                Some(Self::alignment_square(pos))
-            } else {
-                None
-            }
+        let coordinates = Self::ALIGNMENT_COORDINATES
+            .get(self.0 as usize)
+            .copied()
+            .unwrap_or_default();
+        let last = coordinates.len().saturating_sub(1);
+        let overlaps_finder =
+            move |i: usize, j: usize| (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+        coordinates.iter().enumerate().flat_map(move |(i, y)| {
+            coordinates
+                .iter()
+                .enumerate()
+                .filter(move |(j, _)| !overlaps_finder(i, *j))
+                .map(move |(_, x)| Self::alignment_square((*x, *y)))
+        })
+    }
+
+    //18 bit version number with (18,6) Golay code error correction bits, from version 7
+    pub fn version_info_bits(&self) -> u32 {
+        const GENERATOR: u32 = 0x1F25;
+        let version = self.0 as u32;
+        let mut rem = version;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * GENERATOR);
+        }
+        (version << 12) | rem
+    }
+
+    //version of the version info word nearest any of copies, None when more than 3 bits differ
+    pub fn nearest_version(copies: &[u32]) -> Option<Version> {
+        (7..=40)
+            .map(Version)
+            .flat_map(|v| {
+                copies
+                    .iter()
+                    .map(move |c| ((c ^ v.version_info_bits()).count_ones(), v))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .filter(|(distance, _)| *distance <= 3)
+            .map(|(_, v)| v)
+    }
+
+    pub fn has_version_info(&self) -> bool {
+        self.0 >= 7
+    }
+
+    //two 6x3 blocks, above the bottom left finder and left of the top right finder
+    fn version_info_iter(&self) -> impl Iterator<Item = (u8, u8, bool)> {
+        let size = self.square_size();
+        let bits = self.version_info_bits();
+        let count = if self.has_version_info() { 18u8 } else { 0 };
+        (0..count).flat_map(move |i| {
+            let is_dark = 0 != (bits & (1u32 << i));
+            let (a, b) = (size - 11 + i % 3, i / 3);
+            [(a, b, is_dark), (b, a, is_dark)]
         })
     }
 
@@ -318,33 +623,67 @@ impl Version {
         iter.filter(move |pos| v.is_data_location(*pos))
     }
 
+    //data region in placement order with the codeword and block of each module at err_level
+    pub fn data_module_types(
+        &self,
+        err_level: ErrorLevel,
+    ) -> impl Iterator<Item = ((u8, u8), ModuleType)> {
+        let version = self.0;
+        let data_words = err_level.data_code_words(version);
+        let total_words = err_level.total_words(version);
+        self.data_region_iter().enumerate().map(move |(i, pos)| {
+            let codeword = i / 8;
+            let block = err_level.codeword_block(version, codeword) as u8;
+            let module_type = if codeword < data_words {
+                ModuleType::DataCodeword {
+                    codeword: codeword as u16,
+                    block,
+                }
+            } else if codeword < total_words {
+                ModuleType::EccCodeword {
+                    codeword: codeword as u16,
+                    block,
+                }
+            } else {
+                ModuleType::Remainder
+            };
+            (pos, module_type)
+        })
+    }
+
     pub fn reserved_iter(&self) -> impl Iterator<Item = Module> {
-        let to_module: fn((u8, u8, bool)) -> Module =
-            |(x, y, is_dark)| Module::reserved((x, y), is_dark);
+        let to_module =
+            |module_type| move |(x, y, is_dark)| Module::new((x, y), is_dark, module_type);
         let mut finding_pattern_it = self
             .finding_pattern()
-            .flat_map(move |sq| sq.iter_squares().map(to_module));
-        let mut timing_iter = self.timing_pattern_iter().map(to_module);
+            .flat_map(move |sq| sq.iter_squares().map(to_module(ModuleType::Finder)));
+        let mut timing_iter = self
+            .timing_pattern_iter()
+            .map(to_module(ModuleType::Timing));
         let mut alignment_square_iter = self
             .alignment_squares_iter()
-            .flat_map(move |it| it.iter_squares().map(to_module));
+            .flat_map(move |it| it.iter_squares().map(to_module(ModuleType::Alignment)));
 
-        let dark_module = Module::reserved(self.dark_module_location(), true);
+        let dark_module = Module::new(self.dark_module_location(), true, ModuleType::DarkModule);
         let mut seperator_iter = self
             .separator_squares_iter()
-            .map(|(x, y, is_dark)| Module::reserved((x, y), is_dark))
+            .map(to_module(ModuleType::Separator))
             .chain(std::iter::once(dark_module));
+        let mut version_info_iter = self
+            .version_info_iter()
+            .map(to_module(ModuleType::VersionInfo));
         std::iter::from_fn(move || {
             if let Some(v) = finding_pattern_it.next() {
                 Some(v)
-            } else if let Some(v) = alignment_square_iter.next() {
-                Some(v)
             } else if let Some(v) = timing_iter.next() {
                 Some(v)
-            } else if let Some(v) = seperator_iter.next() {
+            } else if let Some(v) = alignment_square_iter.next() {
+                //alignment patterns cross the timing pattern from version 7
+                Some(v)
+            } else if let Some(v) = version_info_iter.next() {
                 Some(v)
             } else {
-                None
+                seperator_iter.next()
             }
         })
     }
@@ -424,7 +763,82 @@ impl ConcentricSquare {
 pub enum EncodingErr {
     NotAscii,
     NotAlphaNumeric,
-    DataTooLong,
+    NotNumeric,
+    NotKanji,
+    InvalidEci,
+    InvalidFnc1,
+    DataTooLong {
+        needed_bits: usize,
+        capacity_bits: usize,
+    },
+    BufferTooSmall {
+        needed_bytes: usize,
+        available_bytes: usize,
+    },
+    InvalidStructuredAppend,
+    ParityMismatch,
+    UnsupportedVersion(u8),
+    InvalidMask,
+    Io(std::io::Error),
+    InvalidMatrix,
+    InvalidFormat,
+    TooManyErrors,
+    InvalidData,
+    InvalidBitCount(u8),
+    InvalidImage,
+}
+
+impl std::fmt::Display for EncodingErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotAscii => write!(f, "data is not ASCII"),
+            NotAlphaNumeric => write!(f, "data is not in the alphanumeric character set"),
+            NotNumeric => write!(f, "data is not decimal digits"),
+            NotKanji => write!(f, "data is not double byte Shift JIS kanji"),
+            InvalidEci => write!(f, "ECI designator is above {}", Eci::MAX.0),
+            InvalidFnc1 => write!(f, "FNC1 application indicator is not 2 digits or a letter"),
+            DataTooLong {
+                needed_bits,
+                capacity_bits,
+            } => write!(
+                f,
+                "data needs {needed_bits} bits but only {capacity_bits} bits are available"
+            ),
+            BufferTooSmall {
+                needed_bytes,
+                available_bytes,
+            } => write!(
+                f,
+                "buffer of {available_bytes} bytes is too small, {needed_bytes} bytes needed"
+            ),
+            InvalidStructuredAppend => write!(f, "structured append symbols are inconsistent"),
+            ParityMismatch => write!(f, "structured append parity does not match the data"),
+            UnsupportedVersion(version) => write!(f, "version {version} is not in 1 to 40"),
+            InvalidMask => write!(f, "mask pattern is not in 0 to 7"),
+            Io(err) => write!(f, "io error: {err}"),
+            InvalidMatrix => write!(f, "modules are not a square symbol of version 1 to 40"),
+            InvalidFormat => write!(f, "format information is unreadable"),
+            TooManyErrors => write!(f, "block has more errors than error correction can fix"),
+            InvalidData => write!(f, "data code words are not a valid segment stream"),
+            InvalidBitCount(bits) => write!(f, "cannot read {bits} bits at once, at most 32"),
+            InvalidImage => write!(f, "image is not a valid Netpbm file"),
+        }
+    }
+}
+
+impl std::error::Error for EncodingErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EncodingErr {
+    fn from(err: std::io::Error) -> Self {
+        Io(err)
+    }
 }
 
 pub(crate) struct ZigzagIter {
@@ -435,11 +849,11 @@ pub(crate) struct ZigzagIter {
 
 impl ZigzagIter {
     pub(crate) fn new(size: u8) -> ZigzagIter {
-        return ZigzagIter {
+        ZigzagIter {
             next_position: Some((size - 1, size - 1)), //bottom right corner
             size,
             traverse_up: true,
-        };
+        }
     }
 }
 
@@ -495,47 +909,557 @@ impl Iterator for ZigzagIter {
     }
 }
 
+const SEG_MODE_NUMERIC: u8 = 0b0001;
+const SEG_MODE_ALPHANUMERIC: u8 = 0b0010;
 const SEG_MODE_BYTES: u8 = 0b0100;
+const SEG_MODE_KANJI: u8 = 0b1000;
+const SEG_MODE_ECI: u8 = 0b0111;
+const SEG_MODE_FNC1_FIRST: u8 = 0b0101;
+const SEG_MODE_FNC1_SECOND: u8 = 0b1001;
 
-// encode string to data code words
-//include mode type and padding bits
-pub fn encode_byte_segment(data: &str, out: &mut [u8]) -> Result<usize, EncodingErr> {
-    let char_count = data.chars().count();
-    if char_count > out.len() {
-        return Err(DataTooLong);
+//group separator between GS1 element strings
+const GS: char = '\u{1D}';
+
+//character set of alphanumeric mode, value of char is its index
+const ALPHANUMERIC_CHARS: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Numeric,
+    Alphanumeric,
+    Bytes,
+    Kanji,
+}
+
+impl Mode {
+    fn indicator(&self) -> u8 {
+        match self {
+            Mode::Numeric => SEG_MODE_NUMERIC,
+            Mode::Alphanumeric => SEG_MODE_ALPHANUMERIC,
+            Mode::Bytes => SEG_MODE_BYTES,
+            Mode::Kanji => SEG_MODE_KANJI,
+        }
+    }
+
+    pub(crate) fn from_indicator(indicator: u8) -> Option<Mode> {
+        match indicator {
+            SEG_MODE_NUMERIC => Some(Mode::Numeric),
+            SEG_MODE_ALPHANUMERIC => Some(Mode::Alphanumeric),
+            SEG_MODE_BYTES => Some(Mode::Bytes),
+            SEG_MODE_KANJI => Some(Mode::Kanji),
+            _ => None,
+        }
+    }
+
+    //width of character count field, grows at version 10 and 27
+    pub fn char_count_bits(&self, version: Version) -> u8 {
+        let widths = match self {
+            Mode::Numeric => [10, 12, 14],
+            Mode::Alphanumeric => [9, 11, 13],
+            Mode::Bytes => [8, 16, 16],
+            Mode::Kanji => [8, 10, 12],
+        };
+        match version.0 {
+            0..=9 => widths[0],
+            10..=26 => widths[1],
+            _ => widths[2],
+        }
+    }
+
+    //bits used by segment of char_count characters, including mode indicator and count
+    pub fn segment_bits(&self, char_count: usize, version: Version) -> usize {
+        let data_bits = match self {
+            Mode::Numeric => 10 * (char_count / 3) + [0, 4, 7][char_count % 3],
+            Mode::Alphanumeric => 11 * (char_count / 2) + 6 * (char_count % 2),
+            Mode::Bytes => 8 * char_count,
+            Mode::Kanji => 13 * char_count,
+        };
+        4 + self.char_count_bits(version) as usize + data_bits
+    }
+}
+
+impl Mode {
+    //chars of data encodable in this mode
+    pub fn can_encode(&self, ch: char) -> bool {
+        self.units(ch, false).is_some()
+    }
+
+    //value of character count field, bytes mode counts UTF-8 bytes
+    pub fn char_count(&self, data: &str) -> usize {
+        self.count(data, false)
+    }
+
+    //characters of this mode needed for ch, in FNC1 mode alphanumeric writes GS as %
+    //and escapes % as %%
+    pub(crate) fn units(&self, ch: char, fnc1: bool) -> Option<usize> {
+        match self {
+            Mode::Numeric => ch.is_ascii_digit().then_some(1),
+            Mode::Alphanumeric if fnc1 && ch == GS => Some(1),
+            Mode::Alphanumeric if fnc1 && ch == '%' => Some(2),
+            Mode::Alphanumeric => {
+                (ch.is_ascii() && alphanumeric_value(ch as u8).is_some()).then_some(1)
+            }
+            Mode::Bytes => Some(ch.len_utf8()),
+            Mode::Kanji => sjis::to_shift_jis(ch).map(|_| 1),
+        }
+    }
+
+    fn count(&self, data: &str, fnc1: bool) -> usize {
+        data.chars()
+            .map(|ch| self.units(ch, fnc1).unwrap_or_default())
+            .sum()
+    }
+
+    fn validate(&self, data: &str, fnc1: bool) -> Result<(), EncodingErr> {
+        if data.chars().all(|ch| self.units(ch, fnc1).is_some()) {
+            return Ok(());
+        }
+        match self {
+            Mode::Numeric => Err(NotNumeric),
+            Mode::Alphanumeric => Err(NotAlphaNumeric),
+            Mode::Bytes => Ok(()),
+            Mode::Kanji => Err(NotKanji),
+        }
+    }
+
+    fn write_data(
+        &self,
+        data: &str,
+        fnc1: bool,
+        w: &mut BigEndianBitWriter,
+    ) -> Result<(), EncodingErr> {
+        match self {
+            Mode::Numeric => {
+                for group in data.as_bytes().chunks(3) {
+                    let value = group
+                        .iter()
+                        .fold(0u32, |acc, digit| acc * 10 + (digit - b'0') as u32);
+                    //1, 2 or 3 digits take 4, 7 or 10 bits
+                    w.append_bits(value, [0, 4, 7, 10][group.len()])?;
+                }
+            }
+            Mode::Alphanumeric => {
+                let mut chars = Vec::with_capacity(data.len());
+                for ch in data.bytes() {
+                    match ch {
+                        0x1D if fnc1 => chars.push(b'%'),
+                        b'%' if fnc1 => chars.extend_from_slice(b"%%"),
+                        _ => chars.push(ch),
+                    }
+                }
+                for pair in chars.chunks(2) {
+                    let value = pair.iter().fold(0u32, |acc, ch| {
+                        acc * 45 + alphanumeric_value(*ch).unwrap_or_default() as u32
+                    });
+                    w.append_bits(value, if pair.len() == 2 { 11 } else { 6 })?;
+                }
+            }
+            Mode::Bytes => write_bytes(data.as_bytes(), w)?,
+            Mode::Kanji => {
+                for ch in data.chars() {
+                    let code = sjis::to_shift_jis(ch).unwrap_or_default();
+                    //subtract 0x8140 or 0xC140, msb * 0xC0 + lsb
+                    let code = if code <= 0x9FFC {
+                        code - 0x8140
+                    } else {
+                        code - 0xC140
+                    };
+                    let value = (code >> 8) as u32 * 0xC0 + (code & 0xFF) as u32;
+                    w.append_bits(value, 13)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//extended channel interpretation, selects the character set of the byte segments that follow
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Eci(pub u32);
+
+impl Eci {
+    pub const UTF8: Eci = Eci(26);
+    pub const MAX: Eci = Eci(999_999);
+
+    //bits used by mode indicator and 1, 2 or 3 byte designator
+    pub fn segment_bits(&self) -> usize {
+        4 + match self.0 {
+            0..=127 => 8,
+            128..=16_383 => 16,
+            _ => 24,
+        }
+    }
+
+    fn write(&self, w: &mut BigEndianBitWriter) -> Result<(), EncodingErr> {
+        w.append_bits(SEG_MODE_ECI as u32, 4)?;
+        //designator prefixed 0, 10 or 110
+        match self.0 {
+            0..=127 => w.append_bits(self.0, 8)?,
+            128..=16_383 => w.append_bits((0b10 << 14) | self.0, 16)?,
+            _ => w.append_bits((0b110 << 21) | self.0, 24)?,
+        }
+        Ok(())
+    }
+
+    //byte segments are UTF-8, readers assume ISO-8859-1 unless told otherwise
+    //so any non-ASCII byte, even one in the ISO-8859-1 range like é, gets ECI 26
+    fn for_segments(segments: &[(Mode, &str)]) -> Option<Eci> {
+        segments
+            .iter()
+            .any(|(mode, data)| *mode == Mode::Bytes && !data.is_ascii())
+            .then_some(Eci::UTF8)
+    }
+}
+
+//function 1 character, marks data formatted to GS1 or AIM application standards
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fnc1 {
+    First,
+    //application indicator, 2 digits as 0-99 or a letter as its ASCII value + 100
+    Second(u8),
+}
+
+impl Fnc1 {
+    pub fn application_indicator(indicator: &str) -> Result<Fnc1, EncodingErr> {
+        let value = match indicator.as_bytes() {
+            [ch] if ch.is_ascii_alphabetic() => ch + 100,
+            [d1, d2] if d1.is_ascii_digit() && d2.is_ascii_digit() => (d1 - b'0') * 10 + d2 - b'0',
+            _ => return Err(InvalidFnc1),
+        };
+        Ok(Fnc1::Second(value))
+    }
+
+    pub fn segment_bits(&self) -> usize {
+        match self {
+            Fnc1::First => 4,
+            Fnc1::Second(_) => 12,
+        }
+    }
+
+    fn write(&self, w: &mut BigEndianBitWriter) -> Result<(), EncodingErr> {
+        match self {
+            Fnc1::First => w.append_bits(SEG_MODE_FNC1_FIRST as u32, 4)?,
+            Fnc1::Second(indicator) => {
+                w.append_bits(SEG_MODE_FNC1_SECOND as u32, 4)?;
+                w.append_bits(*indicator as u32, 8)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//one segment of a symbol, data or a header changing how the data that follows is read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Numeric(&'a str),
+    Alphanumeric(&'a str),
+    Bytes(&'a [u8]),
+    Kanji(&'a str),
+    Eci(Eci),
+    Fnc1(Fnc1),
+    StructuredAppend(StructuredAppend),
+}
+
+impl<'a> Segment<'a> {
+    fn text(&self) -> Option<(Mode, &'a str)> {
+        match *self {
+            Segment::Numeric(data) => Some((Mode::Numeric, data)),
+            Segment::Alphanumeric(data) => Some((Mode::Alphanumeric, data)),
+            Segment::Kanji(data) => Some((Mode::Kanji, data)),
+            _ => None,
+        }
+    }
+
+    //mode and value of character count field of data segments
+    fn mode_count(&self, fnc1: bool) -> Option<(Mode, usize)> {
+        match *self {
+            Segment::Bytes(data) => Some((Mode::Bytes, data.len())),
+            _ => self
+                .text()
+                .map(|(mode, data)| (mode, mode.count(data, fnc1))),
+        }
+    }
+
+    //bits used in version, fnc1 when the symbol has an FNC1 segment
+    pub fn segment_bits(&self, version: Version, fnc1: bool) -> usize {
+        match self {
+            Segment::Eci(eci) => eci.segment_bits(),
+            Segment::Fnc1(f) => f.segment_bits(),
+            Segment::StructuredAppend(_) => StructuredAppend::SEGMENT_BITS,
+            _ => self
+                .mode_count(fnc1)
+                .map(|(mode, count)| mode.segment_bits(count, version))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn validate(&self, version: Version, fnc1: bool) -> Result<(), EncodingErr> {
+        match self {
+            Segment::Eci(eci) if eci.0 > Eci::MAX.0 => return Err(InvalidEci),
+            Segment::StructuredAppend(append)
+                if append.total as usize > StructuredAppend::MAX_SYMBOLS
+                    || append.index >= append.total =>
+            {
+                return Err(InvalidStructuredAppend)
+            }
+            _ => {}
+        }
+        if let Some((mode, data)) = self.text() {
+            mode.validate(data, fnc1)?;
+        }
+        match self.mode_count(fnc1) {
+            //count field overflows only past the capacity of every level
+            Some((mode, count)) if count >= (1 << mode.char_count_bits(version)) => {
+                Err(DataTooLong {
+                    needed_bits: mode.segment_bits(count, version),
+                    capacity_bits: ErrorLevel::L.data_bits(version.0),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write(
+        &self,
+        version: Version,
+        fnc1: bool,
+        w: &mut BigEndianBitWriter,
+    ) -> Result<(), EncodingErr> {
+        if let Some((mode, count)) = self.mode_count(fnc1) {
+            w.append_bits(mode.indicator() as u32, 4)?;
+            w.append_bits(count as u32, mode.char_count_bits(version))?;
+        }
+        match self {
+            Segment::Eci(eci) => eci.write(w),
+            Segment::Fnc1(f) => f.write(w),
+            Segment::StructuredAppend(append) => append.write(w),
+            Segment::Bytes(data) => write_bytes(data, w),
+            _ => match self.text() {
+                Some((mode, data)) => mode.write_data(data, fnc1, w),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+//headers written ahead of the segments, in field order
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct SegmentHeaders {
+    pub append: Option<StructuredAppend>,
+    pub eci: Option<Eci>,
+    pub fnc1: Option<Fnc1>,
+}
+
+impl SegmentHeaders {
+    fn with_segments<'a>(&self, segments: &[(Mode, &'a str)]) -> Vec<Segment<'a>> {
+        let headers = [
+            self.append.map(Segment::StructuredAppend),
+            self.eci.map(Segment::Eci),
+            self.fnc1.map(Segment::Fnc1),
+        ];
+        let data = segments.iter().map(|(mode, data)| match mode {
+            Mode::Numeric => Segment::Numeric(data),
+            Mode::Alphanumeric => Segment::Alphanumeric(data),
+            Mode::Bytes => Segment::Bytes(data.as_bytes()),
+            Mode::Kanji => Segment::Kanji(data),
+        });
+        headers.into_iter().flatten().chain(data).collect()
+    }
+}
+
+fn write_bytes(data: &[u8], w: &mut BigEndianBitWriter) -> Result<(), EncodingErr> {
+    for byte in data {
+        w.append_bits(*byte as u32, 8)?;
+    }
+    Ok(())
+}
+
+fn alphanumeric_value(ch: u8) -> Option<u8> {
+    ALPHANUMERIC_CHARS
+        .iter()
+        .position(|c| *c == ch)
+        .map(|i| i as u8)
+}
+
+//4 bit terminator then zero bits up to byte boundary, returns bytes written
+fn terminate(bit_writer: &mut BigEndianBitWriter) -> Result<usize, EncodingErr> {
+    bit_writer.append_bits(0b0000, 4)?; // terminator bits
+    let pad_bits = (8 - bit_writer.bits_written() % 8) % 8;
+    bit_writer.append_bits(0, pad_bits as u8)?;
+    Ok(bit_writer.bits_written() >> 3) //bits/8
+}
+
+//data code words needed for segments in version, including terminator
+//and an ECI header when byte segments are not ASCII
+pub fn segments_words(segments: &[(Mode, &str)], version: Version) -> usize {
+    let headers = SegmentHeaders {
+        eci: Eci::for_segments(segments),
+        ..Default::default()
+    };
+    (header_segments_bits(headers, segments, version) + 4).div_ceil(8)
+}
+
+fn header_segments_bits(
+    headers: SegmentHeaders,
+    segments: &[(Mode, &str)],
+    version: Version,
+) -> usize {
+    segment_list_bits(&headers.with_segments(segments), version)
+}
+
+//bits of segments written in order, without terminator
+pub(crate) fn segment_list_bits(segments: &[Segment], version: Version) -> usize {
+    let fnc1 = has_fnc1(segments);
+    segments.iter().map(|s| s.segment_bits(version, fnc1)).sum()
+}
+
+//alphanumeric segments of a symbol with FNC1 read % as GS
+fn has_fnc1(segments: &[Segment]) -> bool {
+    segments.iter().any(|s| matches!(s, Segment::Fnc1(_)))
+}
+
+// encode segments one after another to data code words
+//each has mode indicator and count field sized for version, ends with terminator and padding bits
+//an ECI header for UTF-8 is added first when byte segments are not ASCII
+pub fn encode_segments(
+    segments: &[(Mode, &str)],
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    let headers = SegmentHeaders {
+        eci: Eci::for_segments(segments),
+        ..Default::default()
+    };
+    write_segments(headers, segments, version, out)
+}
+
+// encode segments after an ECI header with the given designator
+pub fn encode_eci_segments(
+    eci: Eci,
+    segments: &[(Mode, &str)],
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    let headers = SegmentHeaders {
+        eci: Some(eci),
+        ..Default::default()
+    };
+    write_segments(headers, segments, version, out)
+}
+
+fn write_segments(
+    headers: SegmentHeaders,
+    segments: &[(Mode, &str)],
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    write_segment_list(&headers.with_segments(segments), version, out)
+}
+
+//write segments in order then terminator and padding bits, returns bytes written
+pub(crate) fn write_segment_list(
+    segments: &[Segment],
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    if !version.is_valid() {
+        return Err(UnsupportedVersion(version.0));
+    }
+    let fnc1 = has_fnc1(segments);
+    for segment in segments {
+        segment.validate(version, fnc1)?;
+    }
+    let required_bytes = (segment_list_bits(segments, version) + 4).div_ceil(8);
+    if required_bytes > out.len() {
+        return Err(BufferTooSmall {
+            needed_bytes: required_bytes,
+            available_bytes: out.len(),
+        });
     }
     let mut bit_writer = BigEndianBitWriter::new(out);
-    //bytes
-    bit_writer.append_bits(SEG_MODE_BYTES, 4);
-    bit_writer.append_bits(char_count as u8, 8);
-    for ch in data.chars() {
-        let byte = ch as u8;
-        bit_writer.append_bits(byte, 8);
+    for segment in segments {
+        segment.write(version, fnc1, &mut bit_writer)?;
     }
-    bit_writer.append_bits(0b0000, 4); // terminator bits
+    let bytes = terminate(&mut bit_writer)?;
+    debug_assert!(bytes == required_bytes);
+    Ok(bytes)
+}
 
-    let pad_bits = bit_writer.bits_written() % 8;
-    bit_writer.append_bits(0, pad_bits as u8);
+// encode UTF-8 bytes of string to data code words, 8 bit count field
+//include mode type and padding bits, ECI header when not ASCII
+pub fn encode_byte_segment(data: &str, out: &mut [u8]) -> Result<usize, EncodingErr> {
+    encode_segments(&[(Mode::Bytes, data)], Version(1), out)
+}
+
+// encode binary data as one byte segment, count field is 8 bits up to version 9 and 16 bits after
+//include mode type and padding bits, no ECI header as data is not text
+pub fn encode_binary_segment(
+    data: &[u8],
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    write_segment_list(&[Segment::Bytes(data)], version, out)
+}
+
+// encode decimal digits to data code words, 3 digits packed into 10 bits
+//include mode type and padding bits, count field width depends on version
+pub fn encode_numeric_segment(
+    data: &str,
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    encode_segments(&[(Mode::Numeric, data)], version, out)
+}
+
+// encode 0-9, A-Z, space and $%*+-./: to data code words, 2 chars packed into 11 bits
+//include mode type and padding bits, count field width depends on version
+pub fn encode_alphanumeric_segment(
+    data: &str,
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    encode_segments(&[(Mode::Alphanumeric, data)], version, out)
+}
 
-    let bytes = bit_writer.bits_written() >> 3; //bits/8
-    debug_assert!(bytes == 2 + char_count);
-    return Ok(bytes);
+// encode double byte Shift JIS characters to data code words, 13 bits per char
+//include mode type and padding bits, count field width depends on version
+pub fn encode_kanji_segment(
+    data: &str,
+    version: Version,
+    out: &mut [u8],
+) -> Result<usize, EncodingErr> {
+    encode_segments(&[(Mode::Kanji, data)], version, out)
 }
 
 pub fn add_padding(bytes: &mut [u8]) {
     const PAD_BYTES: [u8; 2] = [0xEC, 0x11];
     for i in 0..bytes.len() {
-        let b = PAD_BYTES[(i & 1)]; //cycle between odd and even
+        let b = PAD_BYTES[i & 1]; //cycle between odd and even
         bytes[i] = b;
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)] //tests compare with true and false
 mod tests;
 
-pub static MASK_FN: [fn((u8, u8)) -> bool; 4] = [
-    |(x, y)| 0 == ((x + y) % 2),
-    |(_, y)| 0 == (y % 2),
-    |(x, _)| 0 == (x % 3),
-    |(x, y)| 0 == ((x + y) % 3),
+//mask functions take (x, y) ie (column, row)
+pub static MASK_FN: [fn((u8, u8)) -> bool; 8] = [
+    |(x, y)| (x as u32 + y as u32).is_multiple_of(2),
+    |(_, y)| y.is_multiple_of(2),
+    |(x, _)| x.is_multiple_of(3),
+    |(x, y)| (x as u32 + y as u32).is_multiple_of(3),
+    |(x, y)| (y as u32 / 2 + x as u32 / 3).is_multiple_of(2),
+    |(x, y)| {
+        let p = x as u32 * y as u32;
+        0 == (p % 2 + p % 3)
+    },
+    |(x, y)| {
+        let p = x as u32 * y as u32;
+        (p % 2 + p % 3).is_multiple_of(2)
+    },
+    |(x, y)| {
+        let (x, y) = (x as u32, y as u32);
+        ((x + y) % 2 + (x * y) % 3).is_multiple_of(2)
+    },
 ];
@@ -0,0 +1,159 @@
+//mask penalty evaluation ISO/IEC 18004 section 7.8.3
+//modules are row major, true for dark
+const N1: u32 = 3;
+const N2: u32 = 3;
+const N3: u32 = 40;
+const N4: u32 = 10;
+
+pub fn penalty_score(modules: &[bool], size: usize) -> u32 {
+    debug_assert!(modules.len() == size * size, "modules must be size x size");
+    run_penalty(modules, size)
+        + block_penalty(modules, size)
+        + finder_like_penalty(modules, size)
+        + balance_penalty(modules)
+}
+
+fn at(modules: &[bool], size: usize, x: usize, y: usize, vertical: bool) -> bool {
+    if vertical {
+        modules[x * size + y]
+    } else {
+        modules[y * size + x]
+    }
+}
+
+//5 or more adjacent modules of same colour in a row or column, N1 + (run - 5)
+fn run_penalty(modules: &[bool], size: usize) -> u32 {
+    let mut penalty = 0;
+    for vertical in [false, true] {
+        for y in 0..size {
+            let mut run_colour = at(modules, size, 0, y, vertical);
+            let mut run_len = 1;
+            for x in 1..size {
+                let colour = at(modules, size, x, y, vertical);
+                if colour == run_colour {
+                    run_len += 1;
+                    continue;
+                }
+                if run_len >= 5 {
+                    penalty += N1 + (run_len - 5);
+                }
+                run_colour = colour;
+                run_len = 1;
+            }
+            if run_len >= 5 {
+                penalty += N1 + (run_len - 5);
+            }
+        }
+    }
+    penalty
+}
+
+//every 2x2 block of same colour, blocks may overlap
+fn block_penalty(modules: &[bool], size: usize) -> u32 {
+    let mut penalty = 0;
+    for y in 0..(size - 1) {
+        for x in 0..(size - 1) {
+            let colour = modules[y * size + x];
+            if colour == modules[y * size + x + 1]
+                && colour == modules[(y + 1) * size + x]
+                && colour == modules[(y + 1) * size + x + 1]
+            {
+                penalty += N2;
+            }
+        }
+    }
+    penalty
+}
+
+//dark:light:dark:dark:dark:light:dark with 4 light modules on either side
+//rows and columns are padded with 4 light modules each side, the quiet zone counts as light
+fn finder_like_penalty(modules: &[bool], size: usize) -> u32 {
+    const PATTERN: u16 = 0b10111010000;
+    const PATTERN_REVERSED: u16 = 0b00001011101;
+    const WINDOW_MASK: u16 = (1 << 11) - 1;
+    let mut penalty = 0;
+    for vertical in [false, true] {
+        for y in 0..size {
+            //an empty window is the leading padding
+            let mut window = 0u16;
+            for x in 0..size + 4 {
+                let bit = u16::from(x < size && at(modules, size, x, y, vertical));
+                window = ((window << 1) | bit) & WINDOW_MASK;
+                if window == PATTERN || window == PATTERN_REVERSED {
+                    penalty += N3;
+                }
+            }
+        }
+    }
+    penalty
+}
+
+//N4 for every 5% the proportion of dark modules deviates from 50%
+fn balance_penalty(modules: &[bool]) -> u32 {
+    let total = modules.len() as u32;
+    let dark = modules.iter().filter(|m| **m).count() as u32;
+    let k = (20 * dark).abs_diff(10 * total) / total;
+    N4 * k
+}
+
+#[cfg(test)]
+mod mask_tests {
+    use crate::mask::{balance_penalty, block_penalty, finder_like_penalty, run_penalty};
+
+    fn grid(rows: &[&str]) -> (Vec<bool>, usize) {
+        let modules: Vec<bool> = rows
+            .iter()
+            .flat_map(|r| r.chars())
+            .map(|c| c == '1')
+            .collect();
+        (modules, rows.len())
+    }
+
+    #[test]
+    fn test_run_penalty() {
+        let (modules, size) = grid(&[
+            "1111110", "0101010", "1010101", "0101010", "1010101", "0101010", "1010100",
+        ]);
+        //row of 6 dark
+        assert_eq!(run_penalty(&modules, size), 4);
+        let (modules, size) = grid(&["10101", "10101", "10101", "10101", "10101"]);
+        //5 columns of runs of 5
+        assert_eq!(run_penalty(&modules, size), 15);
+    }
+
+    #[test]
+    fn test_block_penalty() {
+        let (modules, size) = grid(&["110", "110", "001"]);
+        assert_eq!(block_penalty(&modules, size), 3);
+        let (modules, size) = grid(&["000", "000", "000"]);
+        assert_eq!(block_penalty(&modules, size), 12);
+    }
+
+    #[test]
+    fn test_finder_like_penalty() {
+        let mut rows = vec!["010101010101"; 12];
+        rows[3] = "010111010000";
+        rows[5] = "000010111010";
+        let (modules, size) = grid(&rows);
+        //each pattern also has the quiet zone as its light run on the other side
+        assert_eq!(finder_like_penalty(&modules, size), 160);
+        //pattern touching both edges, light on either side from the quiet zone
+        let mut rows = vec!["0000000"; 7];
+        rows[2] = "1011101";
+        let (modules, size) = grid(&rows);
+        assert_eq!(finder_like_penalty(&modules, size), 80);
+        let (modules, size) = grid(&["0101010"; 7]);
+        assert_eq!(finder_like_penalty(&modules, size), 0);
+    }
+
+    #[test]
+    fn test_balance_penalty() {
+        let (modules, _) = grid(&["1010", "0101", "1010", "0101"]);
+        assert_eq!(balance_penalty(&modules), 0);
+        //13/16 = 81.25% dark, 6 steps of 5% from 50%
+        let (modules, _) = grid(&["1111", "1111", "1111", "1000"]);
+        assert_eq!(balance_penalty(&modules), 60);
+        let (modules, _) = grid(&["0000", "0000", "0000", "0000"]);
+        assert_eq!(balance_penalty(&modules), 100);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::EncodingErr::InvalidMatrix;
+use crate::{EncodingErr, Module, Version};
+use std::fmt;
+
+//modules of a symbol, a bit per module in row major order packed into u64 words
+//function bit is set for every module that is not part of the data region
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QrMatrix {
+    version: Version,
+    dark: Vec<u64>,
+    function: Vec<u64>,
+}
+
+impl QrMatrix {
+    //all modules light data modules
+    pub fn new(version: Version) -> QrMatrix {
+        let size = version.square_size() as usize;
+        let words = (size * size).div_ceil(64);
+        QrMatrix {
+            version,
+            dark: vec![0; words],
+            function: vec![0; words],
+        }
+    }
+
+    //modules later in the iterator replace earlier ones at the same position
+    pub fn from_modules(version: Version, modules: impl Iterator<Item = Module>) -> QrMatrix {
+        let mut matrix = QrMatrix::new(version);
+        for m in modules {
+            matrix.set(m);
+        }
+        matrix
+    }
+
+    //size x size dark modules in row major order, function flags follow the layout of the version
+    pub fn from_bools(size: usize, modules: &[bool]) -> Result<QrMatrix, EncodingErr> {
+        let version = Version(size.saturating_sub(17).div_ceil(4) as u8);
+        if !version.is_valid() || version.square_size() as usize != size {
+            return Err(InvalidMatrix);
+        }
+        if modules.len() != size * size {
+            return Err(InvalidMatrix);
+        }
+        let mut matrix = QrMatrix::new(version);
+        for (i, is_dark) in modules.iter().enumerate() {
+            let (x, y) = ((i % size) as u8, (i / size) as u8);
+            set_bit(&mut matrix.dark, i, *is_dark);
+            set_bit(&mut matrix.function, i, !version.is_data_location((x, y)));
+        }
+        Ok(matrix)
+    }
+
+    //a row per line, # or 1 for dark and . or 0 for light modules, blank lines are skipped
+    pub fn from_text(text: &str) -> Result<QrMatrix, EncodingErr> {
+        let mut size = 0;
+        let mut modules = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            for ch in line.chars() {
+                match ch {
+                    '#' | '1' => modules.push(true),
+                    '.' | '0' => modules.push(false),
+                    _ => return Err(InvalidMatrix),
+                }
+            }
+            size += 1;
+        }
+        QrMatrix::from_bools(size, &modules)
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    //modules per side
+    pub fn size(&self) -> u8 {
+        self.version.square_size()
+    }
+
+    //dark module at column x, row y, positions outside the symbol are light
+    pub fn get(&self, x: u8, y: u8) -> bool {
+        self.index(x, y).is_some_and(|i| bit(&self.dark, i))
+    }
+
+    //module is part of a finder, separator, timing, alignment, format or version pattern
+    pub fn is_function(&self, x: u8, y: u8) -> bool {
+        self.index(x, y).is_some_and(|i| bit(&self.function, i))
+    }
+
+    pub fn set(&mut self, module: Module) {
+        let (x, y) = module.position();
+        if let Some(i) = self.index(x, y) {
+            set_bit(&mut self.dark, i, module.is_dark());
+            set_bit(&mut self.function, i, !module.is_data());
+        }
+    }
+
+    //each row top to bottom, modules left to right
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        let size = self.size();
+        (0..size).map(move |y| (0..size).map(move |x| self.get(x, y)))
+    }
+
+    //dark modules in row major order
+    pub fn to_bools(&self) -> Vec<bool> {
+        self.rows().flatten().collect()
+    }
+
+    fn index(&self, x: u8, y: u8) -> Option<usize> {
+        let size = self.size();
+        (x < size && y < size).then(|| y as usize * size as usize + x as usize)
+    }
+}
+
+//rows of # for dark and . for light modules, read back by from_text
+impl fmt::Display for QrMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            for is_dark in row {
+                write!(f, "{}", if is_dark { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn bit(words: &[u64], i: usize) -> bool {
+    0 != words[i / 64] & (1u64 << (i % 64))
+}
+
+fn set_bit(words: &mut [u64], i: usize, value: bool) {
+    let mask = 1u64 << (i % 64);
+    if value {
+        words[i / 64] |= mask;
+    } else {
+        words[i / 64] &= !mask;
+    }
+}
+
+#[cfg(test)]
+mod matrix_tests {
+    use crate::error_cc::ErrorLevel;
+    use crate::matrix::QrMatrix;
+    use crate::{encode, Module, ModuleType, Version};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_get_and_set() {
+        let mut matrix = QrMatrix::new(Version(1));
+        assert_eq!(matrix.size(), 21);
+        assert!(!matrix.get(20, 20));
+        let data = ModuleType::DataCodeword {
+            codeword: 0,
+            block: 0,
+        };
+        matrix.set(Module::new((20, 20), true, data));
+        matrix.set(Module::new((3, 5), true, ModuleType::Finder));
+        assert!(matrix.get(20, 20) && !matrix.is_function(20, 20));
+        assert!(matrix.get(3, 5) && matrix.is_function(3, 5));
+        assert!(!matrix.get(5, 3));
+        matrix.set(Module::new((20, 20), false, ModuleType::Timing));
+        assert!(!matrix.get(20, 20) && matrix.is_function(20, 20));
+        //outside the symbol
+        matrix.set(Module::new((21, 0), true, data));
+        assert!(!matrix.get(21, 0));
+        assert_eq!(matrix.to_bools().iter().filter(|d| **d).count(), 1);
+    }
+
+    #[test]
+    fn test_code_matrix() {
+        let code = encode("isaiah-perumalla/qrs", ErrorLevel::L).unwrap();
+        let matrix = code.matrix().unwrap();
+        assert_eq!(matrix.version(), Version(2));
+        let rows: Vec<Vec<bool>> = matrix.rows().map(|r| r.collect()).collect();
+        assert_eq!(rows.len(), 25);
+        //top of the finder patterns and the timing pattern between them
+        let top: String = rows[0].iter().map(|d| if *d { '#' } else { '.' }).collect();
+        assert_eq!(&top[0..8], "#######.");
+        assert_eq!(&top[17..], ".#######");
+        let timing: String = rows[6][8..17]
+            .iter()
+            .map(|d| if *d { '#' } else { '.' })
+            .collect();
+        assert_eq!(timing, "#.#.#.#.#");
+        //dark module
+        assert!(matrix.get(8, 17) && matrix.is_function(8, 17));
+        for m in code.module_iter().unwrap() {
+            let (x, y) = m.position();
+            assert_eq!(matrix.is_function(x, y), !m.is_data());
+        }
+        let data_modules = (0..25u8)
+            .flat_map(|y| (0..25u8).map(move |x| (x, y)))
+            .filter(|(x, y)| !matrix.is_function(*x, *y))
+            .count();
+        assert_eq!(data_modules, 44 * 8 + 7);
+
+        let mut set = HashSet::new();
+        set.insert(matrix.clone());
+        assert!(set.contains(&code.matrix().unwrap()));
+        let other = encode("isaiah-perumalla/qrt", ErrorLevel::L).unwrap();
+        assert!(!set.contains(&other.matrix().unwrap()));
+    }
+}
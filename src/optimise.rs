@@ -0,0 +1,216 @@
+use crate::{Mode, Version};
+
+//modes in the order of the cost arrays below
+const MODES: [Mode; 4] = [Mode::Numeric, Mode::Alphanumeric, Mode::Bytes, Mode::Kanji];
+
+//split data into segments with the fewest total bits for the count field widths of version
+//costs are kept in 1/6 bits so numeric (10/3 bits) and alphanumeric (11/2 bits) chars are exact
+//in FNC1 mode GS and % take 1 and 2 alphanumeric chars
+//eci_bits of ECI header are paid once a byte segment holds non-ASCII text, 0 when already present
+pub fn optimal_segments(
+    data: &str,
+    version: Version,
+    kanji: bool,
+    fnc1: bool,
+    eci_bits: usize,
+) -> Vec<(Mode, &str)> {
+    let chars: Vec<(usize, char)> = data.char_indices().collect();
+    if chars.is_empty() {
+        return Vec::new();
+    }
+    let enabled = |m: usize| kanji || MODES[m] != Mode::Kanji;
+    let head_cost = |m: usize| (4 + MODES[m].char_count_bits(version) as u32) * 6;
+    let char_cost = |m: usize, ch: char| -> Option<u32> {
+        if !enabled(m) {
+            return None;
+        }
+        let units = MODES[m].units(ch, fnc1)? as u32;
+        Some(
+            units
+                * match MODES[m] {
+                    Mode::Numeric => 20,
+                    Mode::Alphanumeric => 33,
+                    Mode::Bytes => 48,
+                    Mode::Kanji => 78,
+                },
+        )
+    };
+    //whole bits once a segment ends
+    let round_up = |cost: u32| cost.div_ceil(6) * 6;
+    let eci_cost = eci_bits as u32 * 6;
+
+    //states are a mode, and from STATES / 2 on a mode with the ECI header paid for
+    //costs[s] cheapest encoding of chars so far with the last char in state s
+    //prev_states[i][s] state of char i - 1 on that cheapest path
+    const STATES: usize = 2 * MODES.len();
+    const NONE: u32 = u32::MAX;
+    let mut costs = [NONE; STATES];
+    let mut prev_states: Vec<[u8; STATES]> = Vec::with_capacity(chars.len());
+    for (i, (_, ch)) in chars.iter().enumerate() {
+        let mut next_costs = [NONE; STATES];
+        let mut from = [0u8; STATES];
+        for (m, mode) in MODES.iter().enumerate() {
+            let Some(cost) = char_cost(m, *ch) else {
+                continue;
+            };
+            let needs_eci = *mode == Mode::Bytes && !ch.is_ascii();
+            if i == 0 {
+                let (s, cost) = if needs_eci {
+                    (m + MODES.len(), cost + eci_cost)
+                } else {
+                    (m, cost)
+                };
+                next_costs[s] = head_cost(m) + cost;
+                from[s] = s as u8;
+                continue;
+            }
+            for (f, prev_cost) in costs.iter().enumerate() {
+                if *prev_cost == NONE {
+                    continue;
+                }
+                let paid = f >= MODES.len();
+                let total = if f % MODES.len() == m {
+                    prev_cost + cost
+                } else {
+                    round_up(*prev_cost) + head_cost(m) + cost
+                };
+                let (s, total) = match (paid, needs_eci) {
+                    (true, _) => (m + MODES.len(), total),
+                    (false, true) => (m + MODES.len(), total + eci_cost),
+                    (false, false) => (m, total),
+                };
+                if total < next_costs[s] {
+                    next_costs[s] = total;
+                    from[s] = f as u8;
+                }
+            }
+        }
+        costs = next_costs;
+        prev_states.push(from);
+    }
+
+    //backtrack from cheapest final state, then group runs of same mode
+    let mut state = (0..STATES)
+        .filter(|s| costs[*s] != NONE)
+        .min_by_key(|s| round_up(costs[*s]))
+        .unwrap_or(2);
+    let mut char_modes = vec![0usize; chars.len()];
+    for i in (0..chars.len()).rev() {
+        char_modes[i] = state % MODES.len();
+        state = prev_states[i][state] as usize;
+    }
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 1..=chars.len() {
+        if i == chars.len() || char_modes[i] != char_modes[start] {
+            let end = if i == chars.len() {
+                data.len()
+            } else {
+                chars[i].0
+            };
+            segments.push((MODES[char_modes[start]], &data[chars[start].0..end]));
+            start = i;
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod optimise_tests {
+    use crate::optimise::optimal_segments;
+    use crate::{segments_words, Mode, Version};
+
+    #[test]
+    fn test_single_mode() {
+        let v = Version(1);
+        assert_eq!(
+            optimal_segments("0123456789", v, true, false, 0),
+            [(Mode::Numeric, "0123456789")]
+        );
+        assert_eq!(
+            optimal_segments("HELLO WORLD", v, true, false, 0),
+            [(Mode::Alphanumeric, "HELLO WORLD")]
+        );
+        assert_eq!(
+            optimal_segments("hello", v, true, false, 0),
+            [(Mode::Bytes, "hello")]
+        );
+        assert_eq!(
+            optimal_segments("点茗", v, true, false, 0),
+            [(Mode::Kanji, "点茗")]
+        );
+        assert_eq!(
+            optimal_segments("点茗", v, false, false, 0),
+            [(Mode::Bytes, "点茗")]
+        );
+        assert!(optimal_segments("", v, true, false, 0).is_empty());
+    }
+
+    #[test]
+    fn test_mixed_segments() {
+        let v = Version(1);
+        let segments = optimal_segments("INV-2024-000123456789", v, true, false, 0);
+        assert_eq!(
+            segments,
+            [
+                (Mode::Alphanumeric, "INV-2024-"),
+                (Mode::Numeric, "000123456789")
+            ]
+        );
+        //63 bits alphanumeric, 54 bits numeric
+        assert_eq!(segments_words(&segments, v), 16);
+        assert_eq!(
+            segments_words(&[(Mode::Alphanumeric, "INV-2024-000123456789")], v),
+            17
+        );
+
+        //short digit runs are not worth a mode switch
+        let segments = optimal_segments("a1b", v, true, false, 0);
+        assert_eq!(segments, [(Mode::Bytes, "a1b")]);
+
+        let segments = optimal_segments("order 123456789 点茗", v, true, false, 0);
+        assert_eq!(
+            segments,
+            [
+                (Mode::Bytes, "order "),
+                (Mode::Numeric, "123456789"),
+                (Mode::Alphanumeric, " "),
+                (Mode::Kanji, "点茗")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_width_by_version() {
+        //wider count fields from version 10 make short switches more expensive
+        let data = "abcdefgh123456abcdefgh";
+        let v1 = optimal_segments(data, Version(1), true, false, 0);
+        assert_eq!(v1.len(), 3);
+        let v40 = optimal_segments(data, Version(40), true, false, 0);
+        assert_eq!(v40, [(Mode::Bytes, data)]);
+    }
+
+    #[test]
+    fn test_eci_cost() {
+        let v = Version(1);
+        //76 bits as one byte segment, 78 bits with kanji between
+        assert_eq!(
+            optimal_segments("a点点b", v, true, false, 0),
+            [(Mode::Bytes, "a点点b")]
+        );
+        //a 12 bit ECI header for the UTF-8 bytes makes kanji cheaper
+        assert_eq!(
+            optimal_segments("a点点b", v, true, false, 12),
+            [
+                (Mode::Bytes, "a"),
+                (Mode::Kanji, "点点"),
+                (Mode::Bytes, "b")
+            ]
+        );
+        //ASCII bytes need no ECI
+        assert_eq!(
+            optimal_segments("hello", v, true, false, 12),
+            [(Mode::Bytes, "hello")]
+        );
+    }
+}
@@ -1,6 +1,14 @@
+use crate::builder::QrBuilder;
 use crate::error_cc::ErrorLevel;
-use crate::{encode, encode_byte_segment, ConcentricSquare, Module, Rect, Version, ZigzagIter};
-use std::collections::HashSet;
+use crate::img::ppm::to_img;
+use crate::img::{BLACK, WHITE};
+use crate::{
+    encode, encode_alphanumeric_segment, encode_binary, encode_binary_segment, encode_byte_segment,
+    encode_eci_segments, encode_fnc1, encode_kanji_segment, encode_numeric_segment,
+    encode_with_options, Code, ConcentricSquare, Eci, EncodeOptions, EncodingErr, Fnc1, Mode,
+    Module, ModuleType, Rect, Segment, Version, MASK_FN,
+};
+use std::collections::{HashMap, HashSet};
 
 #[test]
 fn test_concentric_square_iter() {
@@ -86,7 +94,7 @@ fn test_alignment_square() {
     assert_eq!(Version(1).alignment_squares_iter().count(), 0);
     let squares: Vec<ConcentricSquare> = Version(2).alignment_squares_iter().collect();
     assert_eq!(squares.len(), 1);
-    let square = squares.get(0).unwrap();
+    let square = squares.first().unwrap();
 
     let set: HashSet<(u8, u8, bool)> = HashSet::from_iter(square.iter_squares());
     assert_eq!(true, set.contains(&(17, 17, false)));
@@ -524,7 +532,7 @@ pub fn test_data_region_iter() {
 
 #[test]
 pub fn test_encode() {
-    let code = encode::<128>("isaiah-perumalla").unwrap();
+    let code = encode("isaiah-perumalla", ErrorLevel::L).unwrap();
 
     //ErrorLevel::L
     let expected_words = [
@@ -534,10 +542,99 @@ pub fn test_encode() {
     assert_eq!(&expected_words, code.code_words());
 }
 
+//type of each position, later modules replace earlier ones as when drawn
+fn module_types(code: &Code) -> HashMap<(u8, u8), ModuleType> {
+    code.module_iter()
+        .unwrap()
+        .map(|m| (m.position(), m.module_type()))
+        .collect()
+}
+
+fn count_types(types: &HashMap<(u8, u8), ModuleType>) -> [usize; 10] {
+    let mut counts = [0; 10];
+    for t in types.values() {
+        let i = match t {
+            ModuleType::Finder => 0,
+            ModuleType::Separator => 1,
+            ModuleType::Timing => 2,
+            ModuleType::Alignment => 3,
+            ModuleType::FormatInfo => 4,
+            ModuleType::VersionInfo => 5,
+            ModuleType::DarkModule => 6,
+            ModuleType::DataCodeword { .. } => 7,
+            ModuleType::EccCodeword { .. } => 8,
+            ModuleType::Remainder => 9,
+        };
+        counts[i] += 1;
+    }
+    counts
+}
+
+#[test]
+pub fn test_module_types() {
+    let code = encode("isaiah-perumalla", ErrorLevel::L).unwrap();
+    let types = module_types(&code);
+    assert_eq!(types.len(), 21 * 21);
+    //19 data and 7 ecc words
+    assert_eq!(count_types(&types), [147, 45, 10, 0, 30, 0, 1, 152, 56, 0]);
+    let first = ModuleType::DataCodeword {
+        codeword: 0,
+        block: 0,
+    };
+    assert_eq!(types[&(20, 20)], first);
+    assert_eq!(types[&(8, 13)], ModuleType::DarkModule);
+    assert_eq!(types[&(6, 10)], ModuleType::Timing);
+
+    let code = QrBuilder::new()
+        .push(Segment::Bytes(b"isaiah-perumalla"))
+        .min_version(Version(2))
+        .build()
+        .unwrap();
+    let counts = count_types(&module_types(&code));
+    assert_eq!(counts[3], 25);
+    assert_eq!(counts[9], 7);
+
+    //version 7 L, 2 blocks of 78 data words and 20 ecc words
+    let code = QrBuilder::new()
+        .push(Segment::Bytes(b"isaiah-perumalla"))
+        .min_version(Version(7))
+        .build()
+        .unwrap();
+    let types = module_types(&code);
+    assert_eq!(
+        count_types(&types),
+        [147, 45, 48, 150, 30, 36, 1, 156 * 8, 40 * 8, 0]
+    );
+    //alignment patterns over the timing pattern
+    assert_eq!(types[&(22, 6)], ModuleType::Alignment);
+    let block_modules = |block: u8| {
+        types
+            .values()
+            .filter(|t| {
+                matches!(t, ModuleType::DataCodeword { block: b, .. }
+                    | ModuleType::EccCodeword { block: b, .. } if *b == block)
+            })
+            .count()
+    };
+    assert_eq!(block_modules(0), 98 * 8);
+    assert_eq!(block_modules(1), 98 * 8);
+    for m in code.module_iter().unwrap() {
+        if let ModuleType::DataCodeword { codeword, .. }
+        | ModuleType::EccCodeword { codeword, .. } = m.module_type()
+        {
+            assert!((codeword as usize) < code.code_words().len());
+        }
+    }
+}
+
 #[test]
 pub fn test_version_format_modules() {
     let v = Version(1);
-    let modules = v.format_modules(ErrorLevel::L, 0);
+    assert!(matches!(
+        v.format_modules(ErrorLevel::L, 8),
+        Err(EncodingErr::InvalidMask)
+    ));
+    let modules = v.format_modules(ErrorLevel::L, 0).unwrap();
     assert_eq!(true, modules.iter().all(|m| !m.is_data()));
 
     println!("{:?}", &modules);
@@ -560,6 +657,219 @@ pub fn test_encode_to_bytes() {
     assert_eq!(&expected_bytes, &out_bytess[0..expected_bytes.len()]);
 }
 
+#[test]
+pub fn test_encode_utf8_bytes_with_eci() {
+    let mut out_bytes = [0u8; 64];
+    //µ is 2 UTF-8 bytes, ECI 26 header ahead of byte segment with count 9
+    let res = encode_byte_segment("lib-µQRs", &mut out_bytes);
+    assert_eq!(res.unwrap(), 13);
+    let expected_bytes = [
+        0x71, 0xA4, 0x09, 0x6C, 0x69, 0x62, 0x2D, 0xC2, 0xB5, 0x51, 0x52, 0x73, 0x00,
+    ];
+    assert_eq!(&expected_bytes, &out_bytes[0..expected_bytes.len()]);
+
+    let code = encode("lib-µQRs", ErrorLevel::L).unwrap();
+    assert_eq!(&code.code_words()[0..3], &[0x71, 0xA4, 0x09]);
+    //ascii needs no ECI
+    let code = encode("lib-QRs", ErrorLevel::L).unwrap();
+    assert_eq!(code.code_words()[0] >> 4, 0b0100);
+
+    //é is in ISO-8859-1 but is still written as 2 UTF-8 bytes under ECI 26
+    let res = encode_byte_segment("café", &mut out_bytes);
+    assert_eq!(res.unwrap(), 9);
+    let expected_bytes = [0x71, 0xA4, 0x05, 0x63, 0x61, 0x66, 0xC3, 0xA9, 0x00];
+    assert_eq!(&expected_bytes, &out_bytes[0..expected_bytes.len()]);
+}
+
+#[test]
+pub fn test_eci_designator_widths() {
+    let mut out_bytes = [0u8; 64];
+    let segments = [(Mode::Bytes, "A")];
+    let res = encode_eci_segments(Eci(1000), &segments, Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x78, 0x3E, 0x84, 0x01, 0x41, 0x00], &out_bytes[0..6]);
+
+    let res = encode_eci_segments(Eci(100_000), &segments, Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 7);
+    assert_eq!(
+        &[0x7C, 0x18, 0x6A, 0x04, 0x01, 0x41, 0x00],
+        &out_bytes[0..7]
+    );
+
+    assert!(matches!(
+        encode_eci_segments(Eci(1_000_000), &segments, Version(1), &mut out_bytes),
+        Err(EncodingErr::InvalidEci)
+    ));
+}
+
+#[test]
+pub fn test_encode_fnc1() {
+    //GS written as %, % escaped as %%
+    let code = encode_fnc1("AB\u{1D}C%", Fnc1::First, ErrorLevel::L).unwrap();
+    let expected_bytes = [0x52, 0x03, 0x1C, 0xDD, 0x75, 0xB5, 0x00, 0xEC];
+    assert_eq!(&expected_bytes, &code.code_words()[0..expected_bytes.len()]);
+
+    let aim = Fnc1::application_indicator("37").unwrap();
+    assert_eq!(aim, Fnc1::Second(37));
+    let code = encode_fnc1("AB\u{1D}C%", aim, ErrorLevel::L).unwrap();
+    let expected_bytes = [0x92, 0x52, 0x03, 0x1C, 0xDD, 0x75, 0xB5, 0x00];
+    assert_eq!(&expected_bytes, &code.code_words()[0..expected_bytes.len()]);
+
+    //GS1 element strings, digits go to a numeric segment after the FNC1 indicator
+    let code = encode_fnc1(
+        "01049123451234591597033130128\u{1D}10ABC123",
+        Fnc1::First,
+        ErrorLevel::M,
+    )
+    .unwrap();
+    assert_eq!(code.code_words()[0], 0x51);
+}
+
+#[test]
+pub fn test_fnc1_application_indicator() {
+    assert_eq!(Fnc1::application_indicator("00").unwrap(), Fnc1::Second(0));
+    assert_eq!(Fnc1::application_indicator("a").unwrap(), Fnc1::Second(197));
+    assert_eq!(Fnc1::application_indicator("Z").unwrap(), Fnc1::Second(190));
+    for invalid in ["", "123", "1a", "%"] {
+        assert!(matches!(
+            Fnc1::application_indicator(invalid),
+            Err(EncodingErr::InvalidFnc1)
+        ));
+    }
+}
+
+#[test]
+pub fn test_encode_binary_segment() {
+    let mut out_bytes = [0u8; 300];
+    let data = [0x00, 0xFF, 0x80];
+    let res = encode_binary_segment(&data, Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 5);
+    assert_eq!(&[0x40, 0x30, 0x0F, 0xF8, 0x00], &out_bytes[0..5]);
+    //16 bit count field from version 10
+    let res = encode_binary_segment(&data, Version(10), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x40, 0x00, 0x30, 0x0F, 0xF8, 0x00], &out_bytes[0..6]);
+
+    let data = [0xAB; 256];
+    assert!(matches!(
+        encode_binary_segment(&data, Version(9), &mut out_bytes),
+        Err(EncodingErr::DataTooLong {
+            needed_bits: 2060,
+            capacity_bits: 1856
+        })
+    ));
+    assert_eq!(
+        encode_binary_segment(&data, Version(10), &mut out_bytes).unwrap(),
+        259
+    );
+}
+
+#[test]
+pub fn test_encode_binary() {
+    let data: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+    let code = encode_binary(&data, ErrorLevel::L).unwrap();
+    //2424 bits, more than the 274 data code words of version 10
+    assert_eq!(code.version.0, 11);
+    assert_eq!(code.code_words()[0], 0x40);
+    //invalid UTF-8 kept as is, no ECI
+    let code = encode_binary(&[0xC3, 0x28], ErrorLevel::L).unwrap();
+    assert_eq!(&code.code_words()[0..4], &[0x40, 0x2C, 0x32, 0x80]);
+}
+
+#[test]
+pub fn test_encode_boost_error_level() {
+    //74 bits of alphanumeric data, 10 words fit version 1 up to level Q
+    let mut options = EncodeOptions {
+        err_level: ErrorLevel::L,
+        boost_err_level: false,
+    };
+    let code = encode_with_options("HELLO WORLD", options).unwrap();
+    assert_eq!((code.version.0, code.err_level), (1, ErrorLevel::L));
+    options.boost_err_level = true;
+    let code = encode_with_options("HELLO WORLD", options).unwrap();
+    assert_eq!((code.version.0, code.err_level), (1, ErrorLevel::Q));
+    //never lowered
+    options.err_level = ErrorLevel::H;
+    let code = encode_with_options("HELLO WORLD", options).unwrap();
+    assert_eq!((code.version.0, code.err_level), (2, ErrorLevel::H));
+}
+
+#[test]
+pub fn test_encode_fills_capacity() {
+    //151 of 152 data bits, terminator cut short
+    let digits = "01234567890123456789012345678901234567890";
+    let code = encode(digits, ErrorLevel::L).unwrap();
+    assert_eq!(code.version.0, 1);
+    let code = encode(&format!("{digits}1"), ErrorLevel::L).unwrap();
+    assert_eq!(code.version.0, 2);
+}
+
+#[test]
+pub fn test_version_code_words() {
+    for v in 1..=40 {
+        assert_eq!(
+            Version(v).code_words(),
+            ErrorLevel::L.total_words(v),
+            "version {v}"
+        );
+    }
+    assert_eq!(Version(0).code_words(), 0);
+    assert_eq!(Version(41).code_words(), 0);
+    //storage sized for an array at compile time
+    const V40: [u8; Version::MAX.code_words()] = [0; 3706];
+    assert_eq!(V40.len(), 3706);
+
+    //code holds exactly the words of the version it selected
+    let code = encode(&"a".repeat(40), ErrorLevel::L).unwrap();
+    assert_eq!(code.version.0, 3);
+    assert_eq!(code.data.len(), 70);
+    let code = encode(&"a".repeat(2953), ErrorLevel::L).unwrap();
+    assert_eq!(code.version.0, 40);
+    assert_eq!(code.code_words().len(), 3706);
+}
+
+#[test]
+pub fn test_encoding_err_details() {
+    let err = encode(&"a".repeat(3000), ErrorLevel::L).err().unwrap();
+    assert!(matches!(
+        err,
+        EncodingErr::DataTooLong {
+            needed_bits: 24020,
+            capacity_bits: 23648
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "data needs 24020 bits but only 23648 bits are available"
+    );
+    assert!(matches!(
+        Version::new(41),
+        Err(EncodingErr::UnsupportedVersion(41))
+    ));
+    assert!(matches!(
+        encode_numeric_segment("1", Version(0), &mut [0u8; 8]),
+        Err(EncodingErr::UnsupportedVersion(0))
+    ));
+}
+
+#[test]
+pub fn test_img_write_error() {
+    struct FailingWriter;
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let code = encode("isaiah", ErrorLevel::L).unwrap();
+    let err = to_img(&code, [WHITE, BLACK], &mut FailingWriter).unwrap_err();
+    assert!(matches!(err, EncodingErr::Io(_)));
+    assert!(std::error::Error::source(&err).is_some());
+    assert_eq!(err.to_string(), "io error: disk full");
+}
+
 #[test]
 fn test_qr_data_module_iter_by_version() {
     for i in 2..=5 {
@@ -714,7 +1024,7 @@ fn expected_data_module_count(version: u8) -> usize {
     debug_assert!(version <= 5, "not implemente for version > 5");
     let aligment_squre_bits = if version > 1 { 25 } else { 0 };
     let square_size = Version(version).square_size() as usize;
-    let timing_sq = 2 * (square_size - 16) as usize;
+    let timing_sq = 2 * (square_size - 16);
     let expected_data_square_count = (square_size * square_size) -
         (3 * 49) //finding module
         - 45 // seperators
@@ -726,10 +1036,10 @@ fn expected_data_module_count(version: u8) -> usize {
 
 #[test]
 fn test_basic_qr_level1() {
-    let code = encode::<64>("isaiah-perumalla").unwrap();
+    let code = encode("isaiah-perumalla", ErrorLevel::L).unwrap();
 
     let mut bit_string = String::new();
-    for ((x, y), bit) in code.data_module_iter() {
+    for ((_x, _y), bit) in code.data_module_iter() {
         if bit {
             bit_string.push('1');
         } else {
@@ -743,9 +1053,9 @@ fn test_basic_qr_level1() {
 
 #[test]
 fn test_basic_qr_level2() {
-    let code = encode::<64>("isaiah-perumalla1/kingsgrove").unwrap();
+    let code = encode("isaiah-perumalla1/kingsgrove", ErrorLevel::L).unwrap();
     assert_eq!(code.version.0, 2);
-    let data_bit_count = ErrorLevel::L.total_words(2) * 8;
+    let _data_bit_count = ErrorLevel::L.total_words(2) * 8;
     let mut bit_string = String::new();
     for (_, bit) in code.data_module_iter() {
         if bit {
@@ -760,9 +1070,9 @@ fn test_basic_qr_level2() {
 
 #[test]
 fn test_basic_qr_level3() {
-    let code = encode::<128>("isaiah-perumalla1/kingsgrove-0dweqweqw").unwrap();
+    let code = encode("isaiah-perumalla1/kingsgrove-0dweqweqw", ErrorLevel::L).unwrap();
     assert_eq!(code.version.0, 3);
-    let data_bit_count = ErrorLevel::L.total_words(3) * 8;
+    let _data_bit_count = ErrorLevel::L.total_words(3) * 8;
     let mut bit_string = String::new();
     for (_, bit) in code.data_module_iter() {
         if bit {
@@ -774,3 +1084,280 @@ fn test_basic_qr_level3() {
     let expected_unmasked_str = "01000010011001101001011100110110000101101001011000010110100000101101011100000110010101110010011101010110110101100001011011000110110001100001001100010010111101101011011010010110111001100111011100110110011101110010011011110111011001100101001011010011000001100100011101110110010101110001011101110110010101110001011101110000111011000001000111101100000100011110110000010001111011000001000111101100000100011110110000010001111011000001000111101100000011001001000110101000111000110010111111100010101011001010001011010011011100110011001001100100011110011011001110011110";
     assert_eq!(expected_unmasked_str, bit_string);
 }
+
+#[test]
+fn test_encode_error_levels() {
+    let data_words = [("isaiah", ErrorLevel::M, 1), ("isaiah", ErrorLevel::H, 1)];
+    for (data, level, version) in data_words {
+        let code = encode(data, level).unwrap();
+        assert_eq!(code.version.0, version);
+        assert_eq!(code.err_level, level);
+        assert_eq!(code.code_words().len(), level.total_words(version));
+
+        let data_size = level.data_code_words(version);
+        let mut ecc = [0u8; 32];
+        let ecc_size = level
+            .compute_ecc(version, &code.code_words()[0..data_size], &mut ecc)
+            .unwrap();
+        assert_eq!(&ecc[0..ecc_size], &code.code_words()[data_size..]);
+    }
+    let code = encode("isaiah-perumalla", ErrorLevel::Q).unwrap();
+    assert_eq!(code.version.0, 2);
+    //version 3-H has 2 blocks of 13 data words
+    let code = encode("isaiah-perumalla", ErrorLevel::H).unwrap();
+    assert_eq!(code.version.0, 3);
+    assert_eq!(code.code_words().len(), 70);
+}
+
+#[test]
+fn test_format_bits_by_level() {
+    //ec level indicator L=01, M=00, Q=11, H=10 then masked with 0x5412
+    let levels = [
+        (ErrorLevel::L, 0b11),
+        (ErrorLevel::M, 0b10),
+        (ErrorLevel::Q, 0b01),
+        (ErrorLevel::H, 0b00),
+    ];
+    for (level, indicator) in levels {
+        for mask in 0..8u32 {
+            let bits = level.format_bits(mask as u8).unwrap();
+            assert_eq!(bits >> 13, indicator, "level {:?}", level);
+            assert_eq!((bits >> 10) & 0b111, mask ^ 0b101, "mask {}", mask);
+        }
+    }
+}
+
+#[test]
+fn test_data_module_count_all_versions() {
+    for v in 1..=40 {
+        let version = Version(v);
+        let count = version.data_region_iter().count();
+        let remainder_bits = match v {
+            2..=6 => 7,
+            14..=20 | 28..=34 => 3,
+            21..=27 => 4,
+            _ => 0,
+        };
+        assert_eq!(
+            count,
+            ErrorLevel::L.total_words(v) * 8 + remainder_bits,
+            "version={}",
+            v
+        );
+
+        //reserved, format and data modules cover every module of the symbol exactly
+        let size = version.square_size() as usize;
+        let reserved: HashSet<(u8, u8)> = version
+            .reserved_iter()
+            .chain(version.format_modules(ErrorLevel::L, 0).unwrap())
+            .map(|m| m.position())
+            .collect();
+        let data: HashSet<(u8, u8)> = version.data_region_iter().collect();
+        assert_eq!(data.len(), count, "duplicate data modules version={}", v);
+        assert!(reserved.is_disjoint(&data), "version={}", v);
+        assert_eq!(reserved.len() + data.len(), size * size, "version={}", v);
+    }
+}
+
+#[test]
+fn test_alignment_squares_large_versions() {
+    assert_eq!(Version(6).alignment_squares_iter().count(), 1);
+    assert_eq!(Version(7).alignment_squares_iter().count(), 6);
+    assert_eq!(Version(14).alignment_squares_iter().count(), 13);
+    assert_eq!(Version(40).alignment_squares_iter().count(), 46);
+    let centers: HashSet<(u8, u8)> = Version(7)
+        .alignment_squares_iter()
+        .map(|sq| sq.center)
+        .collect();
+    assert!(centers.contains(&(22, 6)));
+    assert!(centers.contains(&(38, 38)));
+    assert!(!centers.contains(&(6, 6)));
+    assert!(!centers.contains(&(38, 6)));
+}
+
+#[test]
+fn test_version_info() {
+    assert_eq!(Version(7).version_info_bits(), 0x07C94);
+    assert_eq!(Version(21).version_info_bits(), 0x15683);
+    assert_eq!(Version(40).version_info_bits(), 0x28C69);
+    let v = Version(7);
+    let size = v.square_size();
+    let modules: Vec<Module> = v
+        .reserved_iter()
+        .filter(|m| {
+            let (x, y) = m.position();
+            (x < 6 && y >= size - 11 && y < size - 8) || (y < 6 && x >= size - 11 && x < size - 8)
+        })
+        .collect();
+    assert_eq!(modules.len(), 36);
+    //bit 0 of 000111110010010100 at top right corner of the bottom left block
+    let bit = |pos| {
+        modules
+            .iter()
+            .find(|m| m.position() == pos)
+            .unwrap()
+            .is_dark()
+    };
+    assert!(!bit((0, size - 11)));
+    assert!(!bit((size - 11, 0)));
+    assert!(bit((0, size - 9)));
+    assert!(bit((4, size - 9)));
+    assert!(!bit((5, size - 9)));
+    assert!(!v.is_data_location((size - 10, 3)));
+    assert!(Version(6).is_data_location((Version(6).square_size() - 10, 3)));
+}
+
+#[test]
+fn test_nearest_version() {
+    let bits = Version(21).version_info_bits();
+    assert_eq!(Version::nearest_version(&[bits]), Some(Version(21)));
+    assert_eq!(
+        Version::nearest_version(&[bits ^ 0b10_0000_0100_0000_0001]),
+        Some(Version(21))
+    );
+    assert_eq!(Version::nearest_version(&[bits ^ 0b1111]), None);
+    assert_eq!(
+        Version::nearest_version(&[bits ^ 0b1111, bits ^ 0b1_0000_0000]),
+        Some(Version(21))
+    );
+    //versions below 7 carry no version info
+    assert_eq!(
+        Version::nearest_version(&[Version(6).version_info_bits()]),
+        None
+    );
+}
+
+#[test]
+fn test_encode_large_version() {
+    let data = "https://example.com/inventory/items?id=000123456789&utm_source=label&utm_medium=print&utm_campaign=warehouse-totes-2024";
+    let code = encode(data, ErrorLevel::M).unwrap();
+    assert_eq!(code.version.0, 7);
+    assert_eq!(code.code_words().len(), ErrorLevel::M.total_words(7));
+}
+
+#[test]
+fn test_mask_functions() {
+    //row i = y, column j = x
+    let dark_count = |mask: usize| {
+        (0..6u8)
+            .flat_map(|y| (0..6u8).map(move |x| (x, y)))
+            .filter(|p| MASK_FN[mask](*p))
+            .count()
+    };
+    let expected = [18, 18, 12, 12, 18, 15, 23, 18];
+    for (mask, count) in expected.iter().enumerate() {
+        assert_eq!(dark_count(mask), *count, "mask {}", mask);
+    }
+    //(i/2 + j/3) % 2 with i=1, j=3
+    assert!(!MASK_FN[4]((3, 1)));
+    assert!(MASK_FN[4]((3, 2)));
+    //no overflow on largest symbol
+    assert!(MASK_FN[0]((176, 176)));
+}
+
+#[test]
+fn test_encode_selects_mask() {
+    let code = encode("isaiah-perumalla", ErrorLevel::L).unwrap();
+    assert_eq!(code.mask, code.best_mask().unwrap());
+    assert!(code.mask < 8);
+    let format_bits = ErrorLevel::L.format_bits(code.mask).unwrap();
+    let format_module = code
+        .module_iter()
+        .unwrap()
+        .find(|m| m.position() == (8, 0))
+        .unwrap();
+    assert_eq!(format_module.is_dark(), 0 != (format_bits & 1));
+    //mask set by hand outside 0 to 7
+    let code = Code { mask: 8, ..code };
+    assert!(matches!(code.module_iter(), Err(EncodingErr::InvalidMask)));
+}
+
+#[test]
+pub fn test_encode_numeric_segment() {
+    let mut out_bytes = [0u8; 64];
+    //ISO/IEC 18004 annex I example
+    let res = encode_numeric_segment("01234567", Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x10, 0x20, 0x0C, 0x56, 0x61, 0x80], &out_bytes[0..6]);
+
+    //12 bit count from version 10, 0001 000000001000 0000001100 0101011001 1000011 0000
+    let res = encode_numeric_segment("01234567", Version(10), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x10, 0x08, 0x03, 0x15, 0x98, 0x60], &out_bytes[0..6]);
+
+    assert!(matches!(
+        encode_numeric_segment("0123A", Version(1), &mut out_bytes),
+        Err(EncodingErr::NotNumeric)
+    ));
+    assert!(matches!(
+        encode_numeric_segment("01234567", Version(1), &mut out_bytes[0..4]),
+        Err(EncodingErr::BufferTooSmall {
+            needed_bytes: 6,
+            available_bytes: 4
+        })
+    ));
+}
+
+#[test]
+fn test_encode_numeric_picks_smaller_version() {
+    let serial = "004512378845120098765432";
+    let code = encode(serial, ErrorLevel::H).unwrap();
+    assert_eq!(code.version.0, 2);
+    assert_eq!(code.code_words()[0] >> 4, 0b0001);
+    //bytes need 26 words for version 2-H
+    assert_eq!(Mode::Bytes.segment_bits(serial.len(), Version(2)), 204);
+    assert_eq!(Mode::Numeric.segment_bits(serial.len(), Version(2)), 94);
+}
+
+#[test]
+pub fn test_encode_alphanumeric_segment() {
+    let mut out_bytes = [0u8; 64];
+    let res = encode_alphanumeric_segment("HELLO WORLD", Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 10);
+    let expected = [0x20, 0x5B, 0x0B, 0x78, 0xD1, 0x72, 0xDC, 0x4D, 0x43, 0x40];
+    assert_eq!(&expected, &out_bytes[0..10]);
+
+    //0010 000000101 00111001110 11100111001 000010 + terminator
+    let res = encode_alphanumeric_segment("AC-42", Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x20, 0x29, 0xCE, 0xE7, 0x21, 0x00], &out_bytes[0..6]);
+
+    assert!(matches!(
+        encode_alphanumeric_segment("hello", Version(1), &mut out_bytes),
+        Err(EncodingErr::NotAlphaNumeric)
+    ));
+}
+
+#[test]
+fn test_encode_picks_alphanumeric() {
+    let code = encode("HTTPS://EXAMPLE.COM/PART/AB-12345", ErrorLevel::L).unwrap();
+    assert_eq!(code.code_words()[0] >> 4, 0b0010);
+    assert_eq!(code.version.0, 2);
+    let code = encode("https://example.com/part/ab-12345", ErrorLevel::L).unwrap();
+    assert_eq!(code.code_words()[0] >> 4, 0b0100);
+    //trailing digits move to a numeric segment, saving enough to stay in version 2
+    assert_eq!(code.version.0, 2);
+}
+
+#[test]
+pub fn test_encode_kanji_segment() {
+    let mut out_bytes = [0u8; 64];
+    //ISO/IEC 18004 example, 点 0x935F -> 0xD9F, 茗 0xE4AA -> 0x1AAA
+    let res = encode_kanji_segment("点茗", Version(1), &mut out_bytes);
+    assert_eq!(res.unwrap(), 6);
+    assert_eq!(&[0x80, 0x26, 0xCF, 0xEA, 0xA8, 0x00], &out_bytes[0..6]);
+
+    assert!(matches!(
+        encode_kanji_segment("点A", Version(1), &mut out_bytes),
+        Err(EncodingErr::NotKanji)
+    ));
+}
+
+#[test]
+fn test_encode_picks_kanji() {
+    let label = "製品番号と賞味期限";
+    //9 chars in 129 bits, fits 19 words of 1-L, bytes would need 27 UTF-8 bytes
+    let code = encode(label, ErrorLevel::L).unwrap();
+    assert_eq!(code.code_words()[0] >> 4, 0b1000);
+    assert_eq!(code.version.0, 1);
+}